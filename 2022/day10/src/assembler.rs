@@ -0,0 +1,411 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Display;
+
+use crate::machine::{Instruction, InstructionSet, Op, Opcode, Operand, Register};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    UnknownMnemonic(String),
+    WrongArgumentCount {
+        mnemonic: String,
+        expected: usize,
+        found: usize,
+    },
+    InvalidRegister(String),
+    InvalidOperand(String),
+    InvalidLabel(String),
+    DuplicateLabel(String),
+    UndefinedLabel(String),
+    TargetOutOfRange(i64),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub line: usize,
+    pub kind: ErrorKind,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            ErrorKind::UnknownMnemonic(m) => write!(f, "unknown instruction `{}`", m),
+            ErrorKind::WrongArgumentCount {
+                mnemonic,
+                expected,
+                found,
+            } => write!(
+                f,
+                "`{}` takes {} argument(s), found {}",
+                mnemonic, expected, found
+            ),
+            ErrorKind::InvalidRegister(r) => write!(f, "`{}` is not a register", r),
+            ErrorKind::InvalidOperand(o) => {
+                write!(f, "`{}` is neither a register nor a number", o)
+            }
+            ErrorKind::InvalidLabel(l) => write!(f, "`{}` is not a valid label", l),
+            ErrorKind::DuplicateLabel(l) => write!(f, "label `{}` is defined twice", l),
+            ErrorKind::UndefinedLabel(l) => write!(f, "label `{}` is never defined", l),
+            ErrorKind::TargetOutOfRange(t) => {
+                write!(f, "jump lands before the start of the program ({})", t)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+fn parse_register(input: &str) -> Result<Register, ErrorKind> {
+    let mut chars = input.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_lowercase() => Ok(c),
+        _ => Err(ErrorKind::InvalidRegister(input.to_owned())),
+    }
+}
+
+fn parse_operand(input: &str) -> Result<Operand, ErrorKind> {
+    match input.parse::<i64>() {
+        Ok(n) => Ok(Operand::Value(n)),
+        Err(_) => parse_register(input)
+            .map(Operand::Register)
+            .map_err(|_| ErrorKind::InvalidOperand(input.to_owned())),
+    }
+}
+
+// Numbers are offsets relative to the jump itself, anything else is a label
+fn parse_target(
+    input: &str,
+    index: usize,
+    labels: &HashMap<&str, usize>,
+) -> Result<usize, ErrorKind> {
+    match input.parse::<i64>() {
+        Ok(offset) => {
+            let target = index as i64 + offset;
+            usize::try_from(target).map_err(|_| ErrorKind::TargetOutOfRange(target))
+        }
+        Err(_) => labels
+            .get(input)
+            .copied()
+            .ok_or_else(|| ErrorKind::UndefinedLabel(input.to_owned())),
+    }
+}
+
+fn is_label(input: &str) -> bool {
+    input.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && input.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn strip_comment(line: &str) -> &str {
+    line.split(['#', ';']).next().unwrap_or_default().trim()
+}
+
+// Mnemonics that aren't bound to a register take it as their first argument
+fn register_and_operand(
+    bound: Option<Register>,
+    args: &[&str],
+) -> Result<(Register, Operand), ErrorKind> {
+    match bound {
+        Some(r) => Ok((r, parse_operand(args[0])?)),
+        None => Ok((parse_register(args[0])?, parse_operand(args[1])?)),
+    }
+}
+
+fn parse_statement(
+    set: &InstructionSet,
+    statement: &str,
+    index: usize,
+    labels: &HashMap<&str, usize>,
+) -> Result<Instruction, ErrorKind> {
+    let mut tokens = statement.split_whitespace();
+    let mnemonic = tokens.next().unwrap_or_default();
+    let args = tokens.collect::<Vec<_>>();
+
+    let def = set
+        .get(mnemonic)
+        .ok_or_else(|| ErrorKind::UnknownMnemonic(mnemonic.to_owned()))?;
+
+    let expected = match (def.opcode, def.register) {
+        (Opcode::Noop, _) => 0,
+        (Opcode::Jmp, _) => 1,
+        (_, Some(_)) => 1,
+        (_, None) => 2,
+    };
+    if args.len() != expected {
+        return Err(ErrorKind::WrongArgumentCount {
+            mnemonic: mnemonic.to_owned(),
+            expected,
+            found: args.len(),
+        });
+    }
+
+    // The argument count is checked above, so indexing `args` can't go out of bounds
+    let op = match def.opcode {
+        Opcode::Noop => Op::Noop,
+        Opcode::Add => {
+            let (r, v) = register_and_operand(def.register, &args)?;
+            Op::Add(r, v)
+        }
+        Opcode::Mul => {
+            let (r, v) = register_and_operand(def.register, &args)?;
+            Op::Mul(r, v)
+        }
+        Opcode::Set => {
+            let (r, v) = register_and_operand(def.register, &args)?;
+            Op::Set(r, v)
+        }
+        Opcode::Jmp => Op::Jmp(parse_target(args[0], index, labels)?),
+        Opcode::Jnz => match def.register {
+            Some(r) => Op::Jnz(Operand::Register(r), parse_target(args[0], index, labels)?),
+            None => Op::Jnz(
+                parse_operand(args[0])?,
+                parse_target(args[1], index, labels)?,
+            ),
+        },
+    };
+
+    Ok(Instruction {
+        op,
+        duration: def.duration,
+    })
+}
+
+// Turns source text into a program. Everything after `#` or `;` is a comment, and `name:` at the
+// start of a line defines a label pointing at the next instruction, which can be on the same line.
+pub fn assemble(set: &InstructionSet, source: &str) -> Result<Vec<Instruction>, Error> {
+    let mut labels = HashMap::new();
+    let mut statements = vec![];
+
+    for (n, line) in source.lines().enumerate() {
+        let line_number = n + 1;
+        let mut line = strip_comment(line);
+
+        if let Some((label, rest)) = line.split_once(':') {
+            let label = label.trim();
+            let error = |kind| Error {
+                line: line_number,
+                kind,
+            };
+            if !is_label(label) {
+                return Err(error(ErrorKind::InvalidLabel(label.to_owned())));
+            }
+            if labels.insert(label, statements.len()).is_some() {
+                return Err(error(ErrorKind::DuplicateLabel(label.to_owned())));
+            }
+            line = rest.trim();
+        }
+
+        if !line.is_empty() {
+            statements.push((line_number, line));
+        }
+    }
+
+    statements
+        .into_iter()
+        .enumerate()
+        .map(|(index, (line, statement))| {
+            parse_statement(set, statement, index, &labels).map_err(|kind| Error { line, kind })
+        })
+        .collect()
+}
+
+fn operand_to_string(operand: &Operand) -> String {
+    match operand {
+        Operand::Register(r) => r.to_string(),
+        Operand::Value(v) => v.to_string(),
+    }
+}
+
+// Finds the mnemonic for an instruction, preferring ones bound to its register
+fn mnemonic_for<'a>(
+    set: &'a InstructionSet,
+    instruction: &Instruction,
+) -> Option<(&'a str, Option<Register>)> {
+    let (opcode, register) = match &instruction.op {
+        Op::Noop => (Opcode::Noop, None),
+        Op::Add(r, _) => (Opcode::Add, Some(*r)),
+        Op::Mul(r, _) => (Opcode::Mul, Some(*r)),
+        Op::Set(r, _) => (Opcode::Set, Some(*r)),
+        Op::Jmp(_) => (Opcode::Jmp, None),
+        Op::Jnz(Operand::Register(r), _) => (Opcode::Jnz, Some(*r)),
+        Op::Jnz(Operand::Value(_), _) => (Opcode::Jnz, None),
+    };
+
+    set.mnemonics()
+        .filter(|(_, def)| def.opcode == opcode && def.duration == instruction.duration)
+        .filter(|(_, def)| def.register.is_none() || def.register == register)
+        .min_by_key(|(mnemonic, def)| (def.register.is_none(), *mnemonic))
+        .map(|(mnemonic, def)| (mnemonic, def.register))
+}
+
+// Pretty-prints a program so that `assemble` gives it back. Jump targets get `L<n>` labels.
+// Returns `None` if an instruction has no mnemonic with the same duration in the set.
+pub fn disassemble(set: &InstructionSet, program: &[Instruction]) -> Option<String> {
+    let targets = program
+        .iter()
+        .filter_map(|i| match i.op {
+            Op::Jmp(t) | Op::Jnz(_, t) => Some(t),
+            _ => None,
+        })
+        .collect::<BTreeSet<_>>();
+    let label = |target: &usize| {
+        format!(
+            "L{}",
+            targets.iter().position(|t| t == target).unwrap_or_default()
+        )
+    };
+
+    let mut out = String::new();
+    for (index, instruction) in program.iter().enumerate() {
+        if targets.contains(&index) {
+            out += &format!("{}:\n", label(&index));
+        }
+
+        let (mnemonic, bound) = mnemonic_for(set, instruction)?;
+        let mut args = vec![];
+        match &instruction.op {
+            Op::Noop => (),
+            Op::Add(r, v) | Op::Mul(r, v) | Op::Set(r, v) => {
+                if bound.is_none() {
+                    args.push(r.to_string());
+                }
+                args.push(operand_to_string(v));
+            }
+            Op::Jmp(t) => args.push(label(t)),
+            Op::Jnz(v, t) => {
+                if bound.is_none() {
+                    args.push(operand_to_string(v));
+                }
+                args.push(label(t));
+            }
+        }
+
+        out += &format!("    {}", mnemonic);
+        if !args.is_empty() {
+            out += &format!(" {}", args.join(" "));
+        }
+        out += "\n";
+    }
+
+    // Jumps past the last instruction halt the machine
+    for target in targets.iter().filter(|t| **t >= program.len()) {
+        out += &format!("{}:\n", label(target));
+    }
+
+    Some(out)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::machine::{Instruction, InstructionSet, Op, Operand};
+
+    use super::{assemble, disassemble, Error, ErrorKind};
+
+    #[test]
+    fn assemble_day10() {
+        let program = assemble(&InstructionSet::day10(), "noop\naddx 3\naddx -5\n").unwrap();
+
+        assert_eq!(
+            program,
+            vec![
+                Instruction {
+                    op: Op::Noop,
+                    duration: 1
+                },
+                Instruction {
+                    op: Op::Add('x', Operand::Value(3)),
+                    duration: 2
+                },
+                Instruction {
+                    op: Op::Add('x', Operand::Value(-5)),
+                    duration: 2
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn labels_and_comments() {
+        let input =
+            "set a 3 # counter\n\nloop: addy 2\n  ; decrement\nadd a -1\njnz a loop\njmp 1\n";
+        let program = assemble(&InstructionSet::extended(), input).unwrap();
+
+        assert_eq!(
+            program.iter().map(|i| &i.op).collect::<Vec<_>>(),
+            vec![
+                &Op::Set('a', Operand::Value(3)),
+                &Op::Add('y', Operand::Value(2)),
+                &Op::Add('a', Operand::Value(-1)),
+                &Op::Jnz(Operand::Register('a'), 1),
+                &Op::Jmp(5),
+            ]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let set = InstructionSet::extended();
+        let error = |line, kind| Err(Error { line, kind });
+
+        assert_eq!(
+            assemble(&set, "noop\nmulx 3\n"),
+            error(2, ErrorKind::UnknownMnemonic("mulx".to_owned()))
+        );
+        assert_eq!(
+            assemble(&set, "addx\n"),
+            error(
+                1,
+                ErrorKind::WrongArgumentCount {
+                    mnemonic: "addx".to_owned(),
+                    expected: 1,
+                    found: 0
+                }
+            )
+        );
+        assert_eq!(
+            assemble(&set, "mul xy 2\n"),
+            error(1, ErrorKind::InvalidRegister("xy".to_owned()))
+        );
+        assert_eq!(
+            assemble(&set, "addx 1.5\n"),
+            error(1, ErrorKind::InvalidOperand("1.5".to_owned()))
+        );
+        assert_eq!(
+            assemble(&set, "a:\nb c:\n"),
+            error(2, ErrorKind::InvalidLabel("b c".to_owned()))
+        );
+        assert_eq!(
+            assemble(&set, "a: noop\na: noop\n"),
+            error(2, ErrorKind::DuplicateLabel("a".to_owned()))
+        );
+        assert_eq!(
+            assemble(&set, "noop\njmp nowhere\n"),
+            error(2, ErrorKind::UndefinedLabel("nowhere".to_owned()))
+        );
+        assert_eq!(
+            assemble(&set, "noop\njmp -2\n"),
+            error(2, ErrorKind::TargetOutOfRange(-1))
+        );
+    }
+
+    #[test]
+    fn disassemble_round_trip() {
+        let set = InstructionSet::extended();
+        let input = "set a 4\nloop:\nmul y 3\naddx y\nadd a -1\njnz a loop\njnz 1 2\nnoop\n";
+        let program = assemble(&set, input).unwrap();
+
+        let source = disassemble(&set, &program).unwrap();
+
+        assert_eq!(
+            source,
+            "    set a 4\nL0:\n    mul y 3\n    addx y\n    add a -1\n    jnz a L0\n    jnz 1 L1\n    noop\nL1:\n"
+        );
+        assert_eq!(assemble(&set, &source), Ok(program));
+    }
+
+    #[test]
+    fn disassemble_missing_mnemonic() {
+        let program = assemble(&InstructionSet::extended(), "mul x 2\n").unwrap();
+
+        assert_eq!(disassemble(&InstructionSet::day10(), &program), None);
+    }
+}
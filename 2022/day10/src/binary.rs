@@ -0,0 +1,238 @@
+use std::fmt::Display;
+
+use crate::machine::{Instruction, Op, Operand, Register};
+
+// Layout: the magic bytes, then for every instruction its opcode byte followed by the duration
+// and the arguments. Numbers are LEB128 varints (zigzagged when signed), registers a single byte.
+const MAGIC: &[u8; 4] = b"CRT\x01";
+
+const NOOP: u8 = 0;
+const ADD: u8 = 1;
+const MUL: u8 = 2;
+const SET: u8 = 3;
+const JMP: u8 = 4;
+const JNZ: u8 = 5;
+
+const REGISTER: u8 = 0;
+const VALUE: u8 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    BadMagic,
+    UnexpectedEnd,
+    UnknownOpcode(u8),
+    UnknownOperand(u8),
+    InvalidRegister(u8),
+    ZeroDuration,
+    Overflow,
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::BadMagic => write!(f, "not a binary program"),
+            DecodeError::UnexpectedEnd => {
+                write!(f, "the program ends halfway through an instruction")
+            }
+            DecodeError::UnknownOpcode(o) => write!(f, "unknown opcode {}", o),
+            DecodeError::UnknownOperand(o) => write!(f, "unknown operand kind {}", o),
+            DecodeError::InvalidRegister(r) => write!(f, "byte {} is not a register", r),
+            DecodeError::ZeroDuration => write!(f, "instructions take at least one cycle"),
+            DecodeError::Overflow => write!(f, "number too large"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+fn write_unsigned(out: &mut Vec<u8>, mut n: u64) {
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn write_signed(out: &mut Vec<u8>, n: i64) {
+    write_unsigned(out, ((n << 1) ^ (n >> 63)) as u64)
+}
+
+fn write_operand(out: &mut Vec<u8>, operand: &Operand) {
+    match operand {
+        Operand::Register(r) => out.extend([REGISTER, *r as u8]),
+        Operand::Value(v) => {
+            out.push(VALUE);
+            write_signed(out, *v);
+        }
+    }
+}
+
+pub fn encode(program: &[Instruction]) -> Vec<u8> {
+    let mut out = MAGIC.to_vec();
+
+    for instruction in program {
+        let opcode = match instruction.op {
+            Op::Noop => NOOP,
+            Op::Add(..) => ADD,
+            Op::Mul(..) => MUL,
+            Op::Set(..) => SET,
+            Op::Jmp(_) => JMP,
+            Op::Jnz(..) => JNZ,
+        };
+        out.push(opcode);
+        write_unsigned(&mut out, instruction.duration as u64);
+
+        match &instruction.op {
+            Op::Noop => (),
+            Op::Add(r, v) | Op::Mul(r, v) | Op::Set(r, v) => {
+                out.push(*r as u8);
+                write_operand(&mut out, v);
+            }
+            Op::Jmp(t) => write_unsigned(&mut out, *t as u64),
+            Op::Jnz(v, t) => {
+                write_operand(&mut out, v);
+                write_unsigned(&mut out, *t as u64);
+            }
+        }
+    }
+
+    out
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        let (first, rest) = self.bytes.split_first().ok_or(DecodeError::UnexpectedEnd)?;
+        self.bytes = rest;
+        Ok(*first)
+    }
+
+    fn unsigned(&mut self) -> Result<u64, DecodeError> {
+        let mut n = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            n |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(DecodeError::Overflow)
+    }
+
+    fn usize(&mut self) -> Result<usize, DecodeError> {
+        usize::try_from(self.unsigned()?).map_err(|_| DecodeError::Overflow)
+    }
+
+    fn signed(&mut self) -> Result<i64, DecodeError> {
+        let n = self.unsigned()?;
+        Ok((n >> 1) as i64 ^ -((n & 1) as i64))
+    }
+
+    fn register(&mut self) -> Result<Register, DecodeError> {
+        match self.byte()? {
+            r if r.is_ascii_lowercase() => Ok(r as char),
+            r => Err(DecodeError::InvalidRegister(r)),
+        }
+    }
+
+    fn operand(&mut self) -> Result<Operand, DecodeError> {
+        match self.byte()? {
+            REGISTER => Ok(Operand::Register(self.register()?)),
+            VALUE => Ok(Operand::Value(self.signed()?)),
+            tag => Err(DecodeError::UnknownOperand(tag)),
+        }
+    }
+
+    fn instruction(&mut self) -> Result<Instruction, DecodeError> {
+        let opcode = self.byte()?;
+        let duration = self.usize()?;
+        if duration == 0 {
+            return Err(DecodeError::ZeroDuration);
+        }
+        let op = match opcode {
+            NOOP => Op::Noop,
+            ADD => Op::Add(self.register()?, self.operand()?),
+            MUL => Op::Mul(self.register()?, self.operand()?),
+            SET => Op::Set(self.register()?, self.operand()?),
+            JMP => Op::Jmp(self.usize()?),
+            JNZ => Op::Jnz(self.operand()?, self.usize()?),
+            opcode => return Err(DecodeError::UnknownOpcode(opcode)),
+        };
+
+        Ok(Instruction { op, duration })
+    }
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Vec<Instruction>, DecodeError> {
+    let mut reader = Reader {
+        bytes: bytes.strip_prefix(MAGIC).ok_or(DecodeError::BadMagic)?,
+    };
+
+    let mut program = vec![];
+    while !reader.bytes.is_empty() {
+        program.push(reader.instruction()?);
+    }
+
+    Ok(program)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::assembler::assemble;
+    use crate::machine::InstructionSet;
+
+    use super::{decode, encode, is_binary, DecodeError};
+
+    #[test]
+    fn round_trip() {
+        let input = "set a -300\nloop:\nmul y 3\naddx y\nadd a 1\njnz a loop\njnz 1 2\nnoop\n";
+        let program = assemble(&InstructionSet::extended(), input).unwrap();
+
+        let bytes = encode(&program);
+
+        assert!(is_binary(&bytes));
+        assert_eq!(decode(&bytes), Ok(program));
+    }
+
+    #[test]
+    fn compact() {
+        let program = assemble(
+            &InstructionSet::day10(),
+            include_str!("../input-example.txt"),
+        )
+        .unwrap();
+
+        // noop is 2 bytes, small addx 6 bytes
+        assert!(encode(&program).len() < program.len() * 6 + 4);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(decode(b"noop\n"), Err(DecodeError::BadMagic));
+        assert_eq!(decode(b"CRT\x01\x01\x02x"), Err(DecodeError::UnexpectedEnd));
+        assert_eq!(
+            decode(b"CRT\x01\x09\x01"),
+            Err(DecodeError::UnknownOpcode(9))
+        );
+        assert_eq!(
+            decode(b"CRT\x01\x01\x02X\x01\x02"),
+            Err(DecodeError::InvalidRegister(b'X'))
+        );
+        assert_eq!(
+            decode(b"CRT\x01\x01\x02x\x07\x02"),
+            Err(DecodeError::UnknownOperand(7))
+        );
+        assert_eq!(decode(b"CRT\x01\x00\x00"), Err(DecodeError::ZeroDuration));
+    }
+}
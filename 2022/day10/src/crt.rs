@@ -0,0 +1,105 @@
+use std::fmt::Display;
+
+use crate::debugger::Cycle;
+
+pub const WIDTH: usize = 40;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Screen {
+    width: usize,
+    pixels: Vec<bool>,
+}
+
+impl Screen {
+    pub fn new(width: usize, pixels: Vec<bool>) -> Self {
+        Self { width, pixels }
+    }
+
+    // Reads `#` as lit and anything else as dark, short rows are padded with dark pixels
+    pub fn parse(input: &str) -> Self {
+        let rows = input.lines().collect::<Vec<_>>();
+        let width = rows
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or_default();
+
+        Self::new(
+            width,
+            rows.into_iter()
+                .flat_map(|row| {
+                    row.chars()
+                        .map(|c| c == '#')
+                        .chain(std::iter::repeat(false))
+                        .take(width)
+                })
+                .collect(),
+        )
+    }
+
+    pub fn from_cycles<I: IntoIterator<Item = Cycle>>(cycles: I) -> Self {
+        Self::new(WIDTH, cycles.into_iter().map(|c| c.pixel == '#').collect())
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    // A screen without columns, like one parsed from empty input, has no rows either
+    pub fn height(&self) -> usize {
+        match self.width {
+            0 => 0,
+            width => self.pixels.len().div_ceil(width),
+        }
+    }
+
+    pub fn lit(&self, x: usize, y: usize) -> bool {
+        x < self.width
+            && self
+                .pixels
+                .get(y * self.width + x)
+                .copied()
+                .unwrap_or(false)
+    }
+}
+
+impl Display for Screen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.width == 0 {
+            return Ok(());
+        }
+        for (i, row) in self.pixels.chunks(self.width).enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            for lit in row {
+                //using ' ' instead of '.' because it makes it easier to see
+                write!(f, "{}", if *lit { '#' } else { ' ' })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Screen;
+
+    #[test]
+    fn parse() {
+        let screen = Screen::parse("#.#\n.#\n");
+
+        assert_eq!((screen.width(), screen.height()), (3, 2));
+        assert!(screen.lit(2, 0) && !screen.lit(2, 1));
+        assert_eq!(screen.to_string(), "# #\n # ");
+    }
+
+    #[test]
+    fn empty() {
+        for screen in [Screen::parse(""), Screen::new(0, vec![true])] {
+            assert_eq!((screen.width(), screen.height()), (0, 0));
+            assert!(!screen.lit(0, 0));
+            assert_eq!(screen.to_string(), "");
+        }
+    }
+}
@@ -0,0 +1,329 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use crate::machine::{Instruction, Machine, Register, X};
+
+// The puzzle asks for register values *during* a cycle, which is the state before the cycle's
+// instruction finishes. Each traced cycle keeps both sides so there's no guessing which one a
+// number refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    During,
+    After,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cycle {
+    pub cycle: usize,
+    pub pc: usize,
+    pub pixel: char,
+    pub during: BTreeMap<Register, i64>,
+    pub after: BTreeMap<Register, i64>,
+}
+
+impl Cycle {
+    pub fn register(&self, register: Register, view: View) -> i64 {
+        let registers = match view {
+            View::During => &self.during,
+            View::After => &self.after,
+        };
+        registers.get(&register).copied().unwrap_or_default()
+    }
+
+    pub fn during(&self, register: Register) -> i64 {
+        self.register(register, View::During)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    fn holds(&self, left: i64, right: i64) -> bool {
+        match self {
+            Comparison::Eq => left == right,
+            Comparison::Ne => left != right,
+            Comparison::Lt => left < right,
+            Comparison::Le => left <= right,
+            Comparison::Gt => left > right,
+            Comparison::Ge => left >= right,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Breakpoint {
+    Cycle(usize),
+    Register(Register, Comparison, i64),
+}
+
+impl Breakpoint {
+    // Accepts `20`, `cycle=20` or a register condition like `x>5`, `a!=0`, `y<=-2`
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        if let Ok(cycle) = input.strip_prefix("cycle=").unwrap_or(input).parse() {
+            return Some(Self::Cycle(cycle));
+        }
+
+        let mut chars = input.chars();
+        let register = chars.next().filter(char::is_ascii_lowercase)?;
+        let rest = chars.as_str();
+
+        [
+            ("==", Comparison::Eq),
+            ("!=", Comparison::Ne),
+            ("<=", Comparison::Le),
+            (">=", Comparison::Ge),
+            ("<", Comparison::Lt),
+            (">", Comparison::Gt),
+            ("=", Comparison::Eq),
+        ]
+        .into_iter()
+        .find_map(|(symbol, comparison)| {
+            let value = rest.strip_prefix(symbol)?.trim().parse().ok()?;
+            Some(Self::Register(register, comparison, value))
+        })
+    }
+
+    // Checked against the machine right before it runs `machine.cycle`
+    fn hit(&self, machine: &Machine) -> bool {
+        match self {
+            Breakpoint::Cycle(cycle) => machine.cycle == *cycle,
+            Breakpoint::Register(register, comparison, value) => {
+                comparison.holds(machine.register(*register), *value)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    Breakpoint(usize),
+    Halted,
+}
+
+pub struct Debugger<'a> {
+    machine: Machine,
+    program: &'a [Instruction],
+    breakpoints: Vec<Breakpoint>,
+}
+
+impl<'a> Debugger<'a> {
+    pub fn new(program: &'a [Instruction]) -> Self {
+        Self {
+            machine: Machine::new(),
+            program,
+            breakpoints: vec![],
+        }
+    }
+
+    pub fn machine(&self) -> &Machine {
+        &self.machine
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> usize {
+        self.breakpoints.push(breakpoint);
+        self.breakpoints.len() - 1
+    }
+
+    // Runs a single cycle
+    pub fn step(&mut self) -> Option<Cycle> {
+        let cycle = self.machine.cycle;
+        let pc = self.machine.instruction_counter;
+        let pixel = self.machine.char_for();
+        let during = self.machine.registers().clone();
+
+        self.machine.step(self.program)?;
+
+        Some(Cycle {
+            cycle,
+            pc,
+            pixel,
+            during,
+            after: self.machine.registers().clone(),
+        })
+    }
+
+    // Runs at least one cycle, then stops right before the next cycle where a breakpoint holds
+    pub fn resume(&mut self) -> Stop {
+        loop {
+            if self.step().is_none() {
+                return Stop::Halted;
+            }
+
+            if let Some(i) = self.breakpoints.iter().position(|b| b.hit(&self.machine)) {
+                return Stop::Breakpoint(i);
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for Debugger<'a> {
+    type Item = Cycle;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.step()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat {
+    Csv,
+    // One JSON object per line
+    Json,
+}
+
+impl TraceFormat {
+    pub fn parse(input: &str) -> Option<Self> {
+        match input {
+            "csv" => Some(Self::Csv),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+pub fn write_trace<W: Write, I: IntoIterator<Item = Cycle>>(
+    out: &mut W,
+    trace: I,
+    format: TraceFormat,
+    view: View,
+) -> std::io::Result<()> {
+    if format == TraceFormat::Csv {
+        writeln!(out, "cycle,pc,x")?;
+    }
+
+    for cycle in trace {
+        let x = cycle.register(X, view);
+        match format {
+            TraceFormat::Csv => writeln!(out, "{},{},{}", cycle.cycle, cycle.pc, x)?,
+            TraceFormat::Json => writeln!(
+                out,
+                "{{\"cycle\":{},\"pc\":{},\"x\":{}}}",
+                cycle.cycle, cycle.pc, x
+            )?,
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::assembler::assemble;
+    use crate::machine::{InstructionSet, X};
+
+    use super::{write_trace, Breakpoint, Comparison, Debugger, Stop, TraceFormat, View};
+
+    const SMALL: &str = "noop\naddx 3\naddx -5\n";
+
+    #[test]
+    fn during_and_after() {
+        let program = assemble(&InstructionSet::day10(), SMALL).unwrap();
+
+        let trace = Debugger::new(&program)
+            .map(|c| (c.cycle, c.during(X), c.register(X, View::After)))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            trace,
+            vec![(1, 1, 1), (2, 1, 1), (3, 1, 4), (4, 4, 4), (5, 4, -1)]
+        );
+    }
+
+    #[test]
+    fn parse_breakpoint() {
+        assert_eq!(Breakpoint::parse("20"), Some(Breakpoint::Cycle(20)));
+        assert_eq!(Breakpoint::parse("cycle=60"), Some(Breakpoint::Cycle(60)));
+        assert_eq!(
+            Breakpoint::parse("x<=-2"),
+            Some(Breakpoint::Register('x', Comparison::Le, -2))
+        );
+        assert_eq!(
+            Breakpoint::parse("a!=0"),
+            Some(Breakpoint::Register('a', Comparison::Ne, 0))
+        );
+        assert_eq!(Breakpoint::parse("x~3"), None);
+    }
+
+    #[test]
+    fn cycle_breakpoints() {
+        let program = assemble(
+            &InstructionSet::day10(),
+            include_str!("../input-example.txt"),
+        )
+        .unwrap();
+        let mut debugger = Debugger::new(&program);
+        debugger.add_breakpoint(Breakpoint::Cycle(20));
+        debugger.add_breakpoint(Breakpoint::Cycle(60));
+
+        assert_eq!(debugger.resume(), Stop::Breakpoint(0));
+        assert_eq!(debugger.machine().cycle, 20);
+        assert_eq!(debugger.machine().x(), 21);
+
+        let cycle = debugger.step().unwrap();
+        assert_eq!(cycle.cycle, 20);
+        assert_eq!(cycle.during(X), 21);
+
+        assert_eq!(debugger.resume(), Stop::Breakpoint(1));
+        assert_eq!(debugger.machine().x(), 19);
+
+        assert_eq!(debugger.resume(), Stop::Halted);
+    }
+
+    #[test]
+    fn register_breakpoint() {
+        let program = assemble(&InstructionSet::day10(), SMALL).unwrap();
+        let mut debugger = Debugger::new(&program);
+        debugger.add_breakpoint(Breakpoint::Register('x', Comparison::Lt, 0));
+
+        assert_eq!(debugger.resume(), Stop::Breakpoint(0));
+        assert_eq!(debugger.machine().cycle, 6);
+        assert_eq!(debugger.machine().x(), -1);
+    }
+
+    #[test]
+    fn csv_trace() {
+        let program = assemble(&InstructionSet::day10(), SMALL).unwrap();
+        let mut out = vec![];
+
+        write_trace(
+            &mut out,
+            Debugger::new(&program),
+            TraceFormat::Csv,
+            View::After,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "cycle,pc,x\n1,0,1\n2,1,1\n3,1,4\n4,2,4\n5,2,-1\n"
+        );
+    }
+
+    #[test]
+    fn json_trace() {
+        let program = assemble(&InstructionSet::day10(), SMALL).unwrap();
+        let mut out = vec![];
+
+        write_trace(
+            &mut out,
+            Debugger::new(&program).take(2),
+            TraceFormat::Json,
+            View::During,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"cycle\":1,\"pc\":0,\"x\":1}\n{\"cycle\":2,\"pc\":1,\"x\":1}\n"
+        );
+    }
+}
@@ -0,0 +1,257 @@
+use std::io::Write;
+
+use crate::crt::Screen;
+use crate::debugger::Cycle;
+use crate::machine::X;
+
+// Lit pixels are drawn as black ink on a white background, same as the `#` in the terminal
+const WHITE: u8 = 0;
+const BLACK: u8 = 1;
+const BEAM: u8 = 2;
+const SPRITE: u8 = 3;
+
+const PALETTE: [u8; 12] = [
+    0xff, 0xff, 0xff, // white
+    0x00, 0x00, 0x00, // black
+    0xe0, 0x20, 0x20, // beam
+    0xb0, 0xb0, 0xb0, // sprite
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Pbm,
+    Png,
+    Gif,
+}
+
+impl ImageFormat {
+    pub fn from_path(path: &str) -> Option<Self> {
+        match path.rsplit_once('.')?.1 {
+            "pbm" => Some(Self::Pbm),
+            "png" => Some(Self::Png),
+            "gif" => Some(Self::Gif),
+            _ => None,
+        }
+    }
+}
+
+// Blows every pixel up to a `scale`x`scale` square, row by row
+fn scaled<T: Copy>(width: usize, pixels: &[T], scale: usize) -> Vec<T> {
+    pixels
+        .chunks(width)
+        .flat_map(|row| {
+            let row = row
+                .iter()
+                .flat_map(|p| std::iter::repeat_n(*p, scale))
+                .collect::<Vec<_>>();
+            std::iter::repeat_n(row, scale).flatten()
+        })
+        .collect()
+}
+
+fn invalid(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidInput, message)
+}
+
+// Scaled pixels are held in memory a byte each, so images stop at 64 MiB of them
+const MAX_PIXELS: usize = 1 << 26;
+
+// The width and height of the image of `screen` at `scale`, which has to have at least one pixel
+// and at most `MAX_PIXELS`
+fn size(screen: &Screen, scale: usize) -> std::io::Result<(usize, usize)> {
+    if scale == 0 {
+        return Err(invalid("the scale has to be at least 1"));
+    }
+    if screen.height() == 0 {
+        return Err(invalid("there's nothing on the screen"));
+    }
+
+    let too_large = || invalid("the image is too large");
+    let width = screen.width().checked_mul(scale).ok_or_else(too_large)?;
+    let height = screen.height().checked_mul(scale).ok_or_else(too_large)?;
+    match width.checked_mul(height) {
+        Some(pixels) if pixels <= MAX_PIXELS => Ok((width, height)),
+        _ => Err(too_large()),
+    }
+}
+
+fn indexed(screen: &Screen) -> Vec<u8> {
+    (0..screen.height())
+        .flat_map(|y| (0..screen.width()).map(move |x| (x, y)))
+        .map(|(x, y)| if screen.lit(x, y) { BLACK } else { WHITE })
+        .collect()
+}
+
+pub fn write_pbm<W: Write>(out: &mut W, screen: &Screen, scale: usize) -> std::io::Result<()> {
+    let (width, height) = size(screen, scale)?;
+    let pixels = scaled(screen.width(), &indexed(screen), scale);
+
+    writeln!(out, "P1")?;
+    writeln!(out, "{} {}", width, height)?;
+    for row in pixels.chunks(width) {
+        let row = row.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        writeln!(out, "{}", row.join(" "))?;
+    }
+
+    Ok(())
+}
+
+pub fn write_png<W: Write>(out: W, screen: &Screen, scale: usize) -> std::io::Result<()> {
+    let (width, height) = size(screen, scale)?;
+    let dimension = |n| u32::try_from(n).map_err(|_| invalid("the image is too large for a PNG"));
+    let (width, height) = (dimension(width)?, dimension(height)?);
+    let pixels = scaled(screen.width(), &indexed(screen), scale);
+
+    let mut encoder = png::Encoder::new(out, width, height);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(&PALETTE[..]);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&pixels)?;
+
+    Ok(())
+}
+
+// One frame per cycle: everything drawn so far, the sprite on the row being drawn and the pixel
+// the beam is drawing during that cycle
+pub fn write_gif<W: Write>(
+    out: W,
+    cycles: &[Cycle],
+    scale: usize,
+    delay: u16,
+) -> std::io::Result<()> {
+    let screen = Screen::from_cycles(cycles.iter().cloned());
+    let (width, height) = (screen.width(), screen.height());
+    let (image_width, image_height) = size(&screen, scale)?;
+    let dimension = |n| u16::try_from(n).map_err(|_| invalid("the image is too large for a GIF"));
+    let (image_width, image_height) = (dimension(image_width)?, dimension(image_height)?);
+    let drawn = indexed(&screen);
+
+    let mut encoder = gif::Encoder::new(out, image_width, image_height, &PALETTE)
+        .map_err(std::io::Error::other)?;
+    encoder
+        .set_repeat(gif::Repeat::Infinite)
+        .map_err(std::io::Error::other)?;
+
+    for (beam, cycle) in cycles.iter().enumerate() {
+        let row = beam / width;
+        let sprite = cycle.during(X);
+
+        let pixels = (0..width * height)
+            .map(|i| {
+                let column = (i % width) as i64;
+                if i == beam {
+                    BEAM
+                } else if i < beam {
+                    drawn[i]
+                } else if i / width == row && sprite.abs_diff(column) <= 1 {
+                    SPRITE
+                } else {
+                    WHITE
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut frame = gif::Frame::from_indexed_pixels(
+            image_width,
+            image_height,
+            scaled(width, &pixels, scale),
+            None,
+        );
+        frame.delay = delay;
+        encoder.write_frame(&frame).map_err(std::io::Error::other)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::assembler::assemble;
+    use crate::crt::Screen;
+    use crate::debugger::Debugger;
+    use crate::machine::InstructionSet;
+
+    use super::{write_gif, write_pbm, write_png, BLACK, WHITE};
+
+    fn screen() -> Screen {
+        Screen::new(3, vec![true, false, false, false, true, true])
+    }
+
+    #[test]
+    fn pbm() {
+        let mut out = vec![];
+        write_pbm(&mut out, &screen(), 2).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "P1\n6 4\n1 1 0 0 0 0\n1 1 0 0 0 0\n0 0 1 1 1 1\n0 0 1 1 1 1\n"
+        );
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let mut out = vec![];
+        write_png(&mut out, &screen(), 3).unwrap();
+
+        let decoder = png::Decoder::new(&out[..]);
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+
+        assert_eq!((info.width, info.height), (9, 6));
+        assert_eq!(pixels[0], BLACK);
+        assert_eq!(pixels[3], WHITE);
+        assert_eq!(pixels[9 * 5 + 8], BLACK);
+    }
+
+    #[test]
+    fn invalid_sizes() {
+        let empty = Screen::parse("");
+        let program = assemble(&InstructionSet::day10(), "noop\n").unwrap();
+        let cycles = Debugger::new(&program).collect::<Vec<_>>();
+
+        assert!(write_pbm(&mut vec![], &screen(), 0).is_err());
+        assert!(write_pbm(&mut vec![], &empty, 1).is_err());
+        assert!(write_png(vec![], &empty, 2).is_err());
+        assert!(write_png(vec![], &screen(), usize::MAX).is_err());
+        // Fits in a usize but not in memory
+        assert!(write_png(vec![], &screen(), 100_000).is_err());
+        assert!(write_pbm(&mut vec![], &screen(), 100_000).is_err());
+        assert!(write_gif(vec![], &[], 1, 2).is_err());
+        assert!(write_gif(vec![], &cycles, 0, 2).is_err());
+        // 40 pixels wide at 2000 times is more than a GIF can hold
+        assert!(write_gif(vec![], &cycles, 2000, 2).is_err());
+    }
+
+    #[test]
+    fn gif_has_a_frame_per_cycle() {
+        let program = assemble(
+            &InstructionSet::day10(),
+            include_str!("../input-example.txt"),
+        )
+        .unwrap();
+        let cycles = Debugger::new(&program).collect::<Vec<_>>();
+
+        let mut out = vec![];
+        write_gif(&mut out, &cycles, 1, 2).unwrap();
+
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = options.read_info(&out[..]).unwrap();
+        let mut frames = 0;
+        let mut last = vec![];
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            frames += 1;
+            last = frame.buffer.to_vec();
+        }
+
+        assert_eq!(frames, cycles.len());
+        assert_eq!((decoder.width(), decoder.height()), (40, 6));
+        // the last frame only misses the pixel under the beam
+        let screen = Screen::from_cycles(cycles);
+        assert_eq!(last[0], if screen.lit(0, 0) { BLACK } else { WHITE });
+        assert_eq!(last[239], super::BEAM);
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::{BTreeMap, HashMap};
+
+pub type Register = char;
+
+pub const X: Register = 'x';
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    Register(Register),
+    Value(i64),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op {
+    Noop,
+    Add(Register, Operand),
+    Mul(Register, Operand),
+    Set(Register, Operand),
+    // Jump targets are absolute instruction indexes, labels and relative offsets are resolved
+    // by the assembler
+    Jmp(usize),
+    Jnz(Operand, usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub op: Op,
+    pub duration: usize,
+}
+
+impl Instruction {
+    // The two instructions of the day 10 puzzle
+    pub fn noop() -> Self {
+        Self {
+            op: Op::Noop,
+            duration: 1,
+        }
+    }
+
+    pub fn addx(n: i64) -> Self {
+        Self {
+            op: Op::Add(X, Operand::Value(n)),
+            duration: 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opcode {
+    Noop,
+    Add,
+    Mul,
+    Set,
+    Jmp,
+    Jnz,
+}
+
+// One entry of the instruction table. When `register` is set the mnemonic is bound to it (like
+// `addx`) and the register is not written in the program, otherwise it's the first argument.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InstructionDef {
+    pub opcode: Opcode,
+    pub register: Option<Register>,
+    pub duration: usize,
+}
+
+impl InstructionDef {
+    pub fn new(opcode: Opcode, duration: usize) -> Self {
+        Self {
+            opcode,
+            register: None,
+            duration,
+        }
+    }
+
+    pub fn bound_to(opcode: Opcode, register: Register, duration: usize) -> Self {
+        Self {
+            opcode,
+            register: Some(register),
+            duration,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct InstructionSet {
+    table: HashMap<String, InstructionDef>,
+}
+
+impl InstructionSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, mnemonic: &str, def: InstructionDef) -> Self {
+        assert!(def.duration > 0, "instructions take at least one cycle");
+        self.table.insert(mnemonic.to_owned(), def);
+        self
+    }
+
+    // The instruction set of the day 10 puzzle
+    pub fn day10() -> Self {
+        Self::new()
+            .with("noop", InstructionDef::new(Opcode::Noop, 1))
+            .with("addx", InstructionDef::bound_to(Opcode::Add, X, 2))
+    }
+
+    pub fn extended() -> Self {
+        Self::day10()
+            .with("addy", InstructionDef::bound_to(Opcode::Add, 'y', 2))
+            .with("add", InstructionDef::new(Opcode::Add, 2))
+            .with("mul", InstructionDef::new(Opcode::Mul, 3))
+            .with("set", InstructionDef::new(Opcode::Set, 1))
+            .with("jmp", InstructionDef::new(Opcode::Jmp, 1))
+            .with("jnz", InstructionDef::new(Opcode::Jnz, 1))
+    }
+
+    pub fn get(&self, mnemonic: &str) -> Option<&InstructionDef> {
+        self.table.get(mnemonic)
+    }
+
+    pub fn mnemonics(&self) -> impl Iterator<Item = (&str, &InstructionDef)> {
+        self.table
+            .iter()
+            .map(|(mnemonic, def)| (mnemonic.as_str(), def))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Machine {
+    pub cycle: usize,
+    pub instruction_counter: usize,
+    delay: usize,
+    registers: BTreeMap<Register, i64>,
+}
+
+impl Machine {
+    pub fn new() -> Self {
+        Machine {
+            cycle: 1,
+            instruction_counter: 0,
+            delay: 0,
+            registers: BTreeMap::from([(X, 1)]),
+        }
+    }
+
+    pub fn register(&self, register: Register) -> i64 {
+        self.registers.get(&register).copied().unwrap_or_default()
+    }
+
+    pub fn registers(&self) -> &BTreeMap<Register, i64> {
+        &self.registers
+    }
+
+    pub fn x(&self) -> i64 {
+        self.register(X)
+    }
+
+    fn value(&self, operand: &Operand) -> i64 {
+        match operand {
+            Operand::Register(r) => self.register(*r),
+            Operand::Value(v) => *v,
+        }
+    }
+
+    // Registers are 64 bit and wrap around on overflow like a real CPU's would, so any program
+    // runs to the end instead of panicking halfway through
+    fn execute(&mut self, op: &Op) -> usize {
+        let next = self.instruction_counter + 1;
+        match op {
+            Op::Noop => next,
+            Op::Add(r, v) => {
+                let value = self.register(*r).wrapping_add(self.value(v));
+                self.registers.insert(*r, value);
+                next
+            }
+            Op::Mul(r, v) => {
+                let value = self.register(*r).wrapping_mul(self.value(v));
+                self.registers.insert(*r, value);
+                next
+            }
+            Op::Set(r, v) => {
+                let value = self.value(v);
+                self.registers.insert(*r, value);
+                next
+            }
+            Op::Jmp(target) => *target,
+            Op::Jnz(v, target) => {
+                if self.value(v) != 0 {
+                    *target
+                } else {
+                    next
+                }
+            }
+        }
+    }
+
+    pub fn step(&mut self, program: &[Instruction]) -> Option<()> {
+        let instruction = program.get(self.instruction_counter)?;
+        self.cycle += 1;
+
+        if self.delay > 0 {
+            self.delay -= 1;
+        } else {
+            // `Instruction` can be built by hand, so a duration of 0 runs in one cycle like 1 does
+            self.delay = instruction.duration.saturating_sub(1);
+        }
+
+        if self.delay == 0 {
+            self.instruction_counter = self.execute(&instruction.op);
+        }
+
+        Some(())
+    }
+
+    pub fn char_for(&self) -> char {
+        let pixel_pos = (self.cycle - 1) as i64 % 40;
+        if self.x().abs_diff(pixel_pos) <= 1 {
+            '#'
+        } else {
+            ' ' //using ' ' instead of '.' because it makes it easier to see
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::assembler::assemble;
+
+    use super::{Instruction, InstructionDef, InstructionSet, Machine, Op, Opcode, Operand, X};
+
+    fn run_to_end(program: &[Instruction]) -> Machine {
+        let mut machine = Machine::new();
+        while let Some(()) = machine.step(program) {}
+        machine
+    }
+
+    #[test]
+    fn registers_wrap_around() {
+        let input = "set a 9223372036854775807\nadd a 1\nset b a\nmul b -1\nset x a\nnoop\n";
+        let program = assemble(&InstructionSet::extended(), input).unwrap();
+
+        let machine = run_to_end(&program);
+
+        assert_eq!(machine.register('a'), i64::MIN);
+        assert_eq!(machine.register('b'), i64::MIN);
+        assert_eq!(machine.char_for(), ' ');
+    }
+
+    #[test]
+    fn loop_with_registers() {
+        let input = "set a 4\nset y 1\nloop:\nmul y 3\nadd a -1\njnz a loop\naddx y\n";
+        let program = assemble(&InstructionSet::extended(), input).unwrap();
+
+        let machine = run_to_end(&program);
+
+        assert_eq!(machine.register('a'), 0);
+        assert_eq!(machine.register('y'), 81);
+        assert_eq!(machine.x(), 82);
+        // 2 sets, 4 iterations of (mul + add + jnz), final addx
+        assert_eq!(machine.cycle, 1 + 2 + 4 * (3 + 2 + 1) + 2);
+    }
+
+    #[test]
+    fn custom_durations() {
+        let set = InstructionSet::new()
+            .with("nop", InstructionDef::new(Opcode::Noop, 3))
+            .with("incx", InstructionDef::bound_to(Opcode::Add, 'x', 1));
+        let program = assemble(&set, "nop\nincx 1\nincx 1\n").unwrap();
+
+        let machine = run_to_end(&program);
+
+        assert_eq!(machine.x(), 3);
+        assert_eq!(machine.cycle, 6);
+    }
+
+    #[test]
+    fn zero_duration() {
+        let program = [Instruction {
+            op: Op::Add(X, Operand::Value(2)),
+            duration: 0,
+        }];
+
+        let machine = run_to_end(&program);
+
+        assert_eq!(machine.x(), 3);
+        assert_eq!(machine.cycle, 2);
+    }
+}
@@ -1,145 +1,193 @@
-use std::collections::HashMap;
-use std::io::Read;
+mod assembler;
+mod binary;
+mod crt;
+mod debugger;
+mod image;
+mod machine;
+mod ocr;
+mod synth;
+use std::io::{Read, Write};
+
+use self::assembler::assemble;
+use self::crt::Screen;
+use self::debugger::{write_trace, Breakpoint, Debugger, Stop, TraceFormat, View};
+use self::image::ImageFormat;
+use self::machine::{Instruction, InstructionSet, Machine, X};
 
 fn main() {
-    let mut buffer = vec![];
-    std::io::stdin().read_to_end(&mut buffer).unwrap();
-    let input = String::from_utf8(buffer).unwrap();
+    let args = std::env::args().collect::<Vec<_>>();
+
+    // The modes below work on arbitrary programs, either source using the extended instruction
+    // set or the binary encoding, instead of solving the puzzle
+    if args.iter().any(|arg| arg == "--run") {
+        return run(&load(&read_stdin()));
+    }
+
+    if args.iter().any(|arg| arg == "--disassemble") {
+        let source = assembler::disassemble(&InstructionSet::extended(), &load(&read_stdin()))
+            .expect("program only uses the extended instruction set");
+        print!("{}", source);
+        return;
+    }
+
+    // Print a puzzle input that draws the given text, or the image on stdin when there's no text
+    if let Some(text) = arg_values(&args, "--draw-text").next() {
+        return draw(synth::synthesise_text(text));
+    }
+    if args.iter().any(|arg| arg == "--draw") {
+        let target = Screen::parse(std::str::from_utf8(&read_stdin()).unwrap());
+        return draw(synth::synthesise(&target));
+    }
+
+    if let Some(path) = arg_values(&args, "--assemble").next() {
+        return std::fs::write(path, binary::encode(&load(&read_stdin()))).unwrap();
+    }
+
+    if let Some(format) = arg_values(&args, "--trace").next() {
+        let format = TraceFormat::parse(format).expect("trace format is csv or json");
+        let view = if args.iter().any(|arg| arg == "--after") {
+            View::After
+        } else {
+            View::During
+        };
+        return trace(&load(&read_stdin()), format, view);
+    }
+
+    if let Some(path) = arg_values(&args, "--image").next() {
+        let scale = arg_values(&args, "--scale")
+            .next()
+            .map(|s| s.parse().expect("scale is a number"))
+            .unwrap_or(1);
+        return export_image(&load(&read_stdin()), path, scale);
+    }
+
+    let breakpoints = arg_values(&args, "--break")
+        .map(|b| Breakpoint::parse(b).expect("valid breakpoint"))
+        .collect::<Vec<_>>();
+    if !breakpoints.is_empty() {
+        return debug(&load(&read_stdin()), breakpoints);
+    }
+
+    let input = String::from_utf8(read_stdin()).unwrap();
+
     let result_1 = part1(&input);
 
     println!("part1: {}", result_1);
 
-    part2(&input);
+    let result_2 = part2(&input);
+    println!("part2: {}", result_2);
 }
 
-#[derive(Debug)]
-enum Instruction {
-    Noop,
-    Addx(i64),
+fn arg_values<'a>(args: &'a [String], flag: &'a str) -> impl Iterator<Item = &'a str> {
+    args.windows(2)
+        .filter(move |pair| pair[0] == flag)
+        .map(|pair| pair[1].as_str())
 }
 
-impl Instruction {
-    fn parse(input: &str) -> Option<Self> {
-        match input {
-            "noop" => Some(Self::Noop),
-            _ => {
-                let (_, n) = input.split_once(' ')?;
-                Some(Self::Addx(n.parse::<i64>().ok()?))
-            }
-        }
-    }
-    fn duration(&self) -> usize {
-        match self {
-            Instruction::Noop => 1,
-            Instruction::Addx(_) => 2,
-        }
-    }
+// Only the modes that take input read stdin, so the others don't wait for an EOF that may never come
+fn read_stdin() -> Vec<u8> {
+    let mut buffer = vec![];
+    std::io::stdin().read_to_end(&mut buffer).unwrap();
+    buffer
 }
 
-#[derive(Clone, Debug)]
-struct Machine {
-    cycle: usize,
-    instruction_counter: usize,
-    delay: usize,
-    x: i64,
+fn load(buffer: &[u8]) -> Vec<Instruction> {
+    if binary::is_binary(buffer) {
+        return binary::decode(buffer).unwrap_or_else(|e| {
+            eprintln!("invalid binary program: {}", e);
+            std::process::exit(1)
+        });
+    }
+
+    let source = std::str::from_utf8(buffer).expect("program is either text or binary");
+    assemble(&InstructionSet::extended(), source).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1)
+    })
 }
 
-impl Machine {
-    fn new() -> Self {
-        Machine {
-            cycle: 1,
-            instruction_counter: 0,
-            delay: 0,
-            x: 1,
-        }
-    }
+fn draw(program: Option<Vec<Instruction>>) {
+    let program = program.unwrap_or_else(|| {
+        eprintln!("the CRT can't draw that image");
+        std::process::exit(1)
+    });
+    let source = assembler::disassemble(&InstructionSet::day10(), &program)
+        .expect("synthesised programs only use noop and addx");
+    print!("{}", source);
+}
 
-    fn step(&mut self, program: &[Instruction]) -> Option<()> {
-        let instruction = program.get(self.instruction_counter)?;
-        self.cycle += 1;
+fn run(program: &[Instruction]) {
+    let mut machine = Machine::new();
+    while let Some(()) = machine.step(program) {}
 
-        if self.delay > 0 {
-            self.delay -= 1;
-        } else {
-            self.delay = instruction.duration() - 1;
-        }
+    println!("cycles: {}", machine.cycle - 1);
+    for (register, value) in machine.registers() {
+        println!("{}: {}", register, value);
+    }
+}
 
-        if self.delay == 0 {
-            match instruction {
-                Instruction::Noop => (),
-                Instruction::Addx(x) => {
-                    self.x += x;
-                }
-            }
+fn trace(program: &[Instruction], format: TraceFormat, view: View) {
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    write_trace(&mut out, Debugger::new(program), format, view).unwrap();
+    out.flush().unwrap();
+}
 
-            self.instruction_counter += 1;
-        }
+fn export_image(program: &[Instruction], path: &str, scale: usize) {
+    let cycles = Debugger::new(program).collect::<Vec<_>>();
+    let screen = Screen::from_cycles(cycles.iter().cloned());
 
-        Some(())
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path).unwrap());
+    match ImageFormat::from_path(path).expect("image path ends in .pbm, .png or .gif") {
+        ImageFormat::Pbm => image::write_pbm(&mut file, &screen, scale),
+        ImageFormat::Png => image::write_png(&mut file, &screen, scale),
+        ImageFormat::Gif => image::write_gif(&mut file, &cycles, scale, 2),
     }
+    .unwrap_or_else(|e| {
+        eprintln!("can't write {}: {}", path, e);
+        std::process::exit(1)
+    });
+}
 
-    fn char_for(&self) -> char {
-        let pixel_pos = (self.cycle - 1) as i64 % 40;
-        if self.x - 1 <= pixel_pos && pixel_pos <= self.x + 1 {
-            '#'
-        } else {
-            ' ' //using ' ' instead of '.' because it makes it easier to see
-        }
+fn debug(program: &[Instruction], breakpoints: Vec<Breakpoint>) {
+    let mut debugger = Debugger::new(program);
+    for breakpoint in breakpoints {
+        debugger.add_breakpoint(breakpoint);
     }
 
-    fn run(&mut self, program: Vec<Instruction>) -> HashMap<usize, (Machine, char)> {
-        let mut results: HashMap<usize, (Machine, char)> = HashMap::new();
-
-        results.insert(self.cycle, (self.clone(), self.char_for()));
-        while let Some(()) = self.step(&program) {
-            results.insert(self.cycle, (self.clone(), self.char_for()));
-        }
-
-        results
+    while let Stop::Breakpoint(i) = debugger.resume() {
+        let machine = debugger.machine();
+        println!(
+            "breakpoint {} at cycle {}, pc {}: {:?}",
+            i,
+            machine.cycle,
+            machine.instruction_counter,
+            machine.registers()
+        );
     }
 }
 
 fn part1(input: &str) -> i64 {
-    let program = input
-        .lines()
-        .filter_map(Instruction::parse)
-        .collect::<Vec<_>>();
-
-    let inspect_cycles = vec![20, 60, 100, 140, 180, 220];
+    let program = assemble(&InstructionSet::day10(), input).expect("valid program");
 
-    let mut machine = Machine::new();
-    let results = machine.run(program);
+    let inspect_cycles = [20, 60, 100, 140, 180, 220];
 
-    inspect_cycles
-        .into_iter()
-        .filter_map(|c| Some((c, results.get(&c)?)))
-        .fold(0, |acc, (c, (m, _))| acc + ((c as i64) * m.x))
+    Debugger::new(&program)
+        .filter(|c| inspect_cycles.contains(&c.cycle))
+        .fold(0, |acc, c| acc + ((c.cycle as i64) * c.during(X)))
 }
 
-fn part2(input: &str) {
-    let program = input
-        .lines()
-        .filter_map(Instruction::parse)
-        .collect::<Vec<_>>();
+fn part2(input: &str) -> String {
+    let program = assemble(&InstructionSet::day10(), input).expect("valid program");
 
-    let mut machine = Machine::new();
-    let results = machine.run(program);
-
-    print!("part 2:");
-    {
-        let len = results.len();
-        let mut ms: Vec<(Machine, char)> = vec![(Machine::new(), ' '); len];
-        for (k, v) in results.into_iter() {
-            ms[k - 1] = v
-        }
-
-        for (i, (_, c)) in ms.into_iter().enumerate() {
-            if i % 40 == 0 {
-                println!();
-            }
-            print!("{}", c)
-        }
+    let screen = Screen::from_cycles(Debugger::new(&program));
+
+    let letters = ocr::decode_any(&screen).unwrap_or_default();
+    if letters.is_empty() || letters.contains('?') {
+        eprintln!("{}", screen);
     }
+
+    letters
 }
 
 #[cfg(test)]
@@ -150,4 +198,9 @@ mod test {
     fn part1() {
         assert_eq!(super::part1(INPUT), 13140);
     }
+
+    #[test]
+    fn part2() {
+        assert_eq!(super::part2(include_str!("../input.txt")), "BGKAEREZ");
+    }
 }
@@ -0,0 +1,274 @@
+use crate::crt::Screen;
+
+type Glyph = (char, &'static [&'static str]);
+
+pub struct Font {
+    pub width: usize,
+    pub height: usize,
+    // Distance between the first column of two consecutive letters
+    pub pitch: usize,
+    glyphs: &'static [Glyph],
+}
+
+impl Font {
+    fn glyph(&self, letter: char) -> Option<&'static [&'static str]> {
+        self.glyphs
+            .iter()
+            .find(|(c, _)| *c == letter)
+            .map(|(_, rows)| *rows)
+    }
+
+    fn recognise(&self, screen: &Screen, left: usize) -> Option<char> {
+        self.glyphs
+            .iter()
+            .find(|(_, rows)| {
+                rows.iter().enumerate().all(|(y, row)| {
+                    row.chars()
+                        .enumerate()
+                        .all(|(x, c)| (c == '#') == screen.lit(left + x, y))
+                })
+            })
+            .map(|(c, _)| *c)
+    }
+}
+
+// Letters are read cell by cell, `pitch` columns at a time, from the top-left corner of the
+// screen. Cells that don't match any letter of the font are decoded as `?`.
+pub fn decode(screen: &Screen, font: &Font) -> String {
+    (0..screen.width())
+        .step_by(font.pitch)
+        .filter(|left| left + font.width <= screen.width())
+        .map(|left| font.recognise(screen, left).unwrap_or('?'))
+        .collect()
+}
+
+// The inverse of `decode`, letters are laid out from the left on a `width` wide screen. Returns
+// `None` if a letter isn't in the font or the text doesn't fit.
+pub fn render(text: &str, font: &Font, width: usize) -> Option<Screen> {
+    let glyphs = text
+        .chars()
+        .map(|c| font.glyph(c))
+        .collect::<Option<Vec<_>>>()?;
+    if glyphs.len() * font.pitch > width + font.pitch - font.width {
+        return None;
+    }
+
+    let mut pixels = vec![false; width * font.height];
+    for (i, glyph) in glyphs.into_iter().enumerate() {
+        for (y, row) in glyph.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                pixels[y * width + i * font.pitch + x] = c == '#';
+            }
+        }
+    }
+
+    Some(Screen::new(width, pixels))
+}
+
+// Picks the font based on the height of the screen
+pub fn decode_any(screen: &Screen) -> Option<String> {
+    [&SMALL, &LARGE]
+        .into_iter()
+        .find(|font| font.height == screen.height())
+        .map(|font| decode(screen, font))
+}
+
+// The 4x6 font used by most puzzles, including this one
+pub const SMALL: Font = Font {
+    width: 4,
+    height: 6,
+    pitch: 5,
+    glyphs: &[
+        ('A', &[".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+        ('B', &["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+        ('C', &[".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+        ('E', &["####", "#...", "###.", "#...", "#...", "####"]),
+        ('F', &["####", "#...", "###.", "#...", "#...", "#..."]),
+        ('G', &[".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+        ('H', &["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+        ('I', &[".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+        ('J', &["..##", "...#", "...#", "...#", "#..#", ".##."]),
+        ('K', &["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+        ('L', &["#...", "#...", "#...", "#...", "#...", "####"]),
+        ('O', &[".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+        ('P', &["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+        ('R', &["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+        ('S', &[".###", "#...", "#...", ".##.", "...#", "###."]),
+        ('U', &["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+        ('Z', &["####", "...#", "..#.", ".#..", "#...", "####"]),
+    ],
+};
+
+// The 6x10 font from 2018 day 10
+pub const LARGE: Font = Font {
+    width: 6,
+    height: 10,
+    pitch: 8,
+    glyphs: &[
+        (
+            'A',
+            &[
+                "..##..", ".#..#.", "#....#", "#....#", "#....#", "######", "#....#", "#....#",
+                "#....#", "#....#",
+            ],
+        ),
+        (
+            'B',
+            &[
+                "#####.", "#....#", "#....#", "#....#", "#####.", "#....#", "#....#", "#....#",
+                "#....#", "#####.",
+            ],
+        ),
+        (
+            'C',
+            &[
+                ".####.", "#....#", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....",
+                "#....#", ".####.",
+            ],
+        ),
+        (
+            'E',
+            &[
+                "######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....",
+                "#.....", "######",
+            ],
+        ),
+        (
+            'F',
+            &[
+                "######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....",
+                "#.....", "#.....",
+            ],
+        ),
+        (
+            'G',
+            &[
+                ".####.", "#....#", "#.....", "#.....", "#.....", "#..###", "#....#", "#....#",
+                "#...##", ".###.#",
+            ],
+        ),
+        (
+            'H',
+            &[
+                "#....#", "#....#", "#....#", "#....#", "######", "#....#", "#....#", "#....#",
+                "#....#", "#....#",
+            ],
+        ),
+        (
+            'J',
+            &[
+                "...###", "....#.", "....#.", "....#.", "....#.", "....#.", "....#.", "#...#.",
+                "#...#.", ".###..",
+            ],
+        ),
+        (
+            'K',
+            &[
+                "#....#", "#...#.", "#..#..", "#.#...", "##....", "##....", "#.#...", "#..#..",
+                "#...#.", "#....#",
+            ],
+        ),
+        (
+            'L',
+            &[
+                "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....",
+                "#.....", "######",
+            ],
+        ),
+        (
+            'N',
+            &[
+                "#....#", "##...#", "##...#", "#.#..#", "#.#..#", "#..#.#", "#..#.#", "#...##",
+                "#...##", "#....#",
+            ],
+        ),
+        (
+            'P',
+            &[
+                "#####.", "#....#", "#....#", "#....#", "#####.", "#.....", "#.....", "#.....",
+                "#.....", "#.....",
+            ],
+        ),
+        (
+            'R',
+            &[
+                "#####.", "#....#", "#....#", "#....#", "#####.", "#..#..", "#...#.", "#...#.",
+                "#....#", "#....#",
+            ],
+        ),
+        (
+            'X',
+            &[
+                "#....#", "#....#", ".#..#.", ".#..#.", "..##..", "..##..", ".#..#.", ".#..#.",
+                "#....#", "#....#",
+            ],
+        ),
+        (
+            'Z',
+            &[
+                "######", ".....#", ".....#", "....#.", "...#..", "..#...", ".#....", "#.....",
+                "#.....", "######",
+            ],
+        ),
+    ],
+};
+
+#[cfg(test)]
+mod test {
+    use crate::crt::Screen;
+
+    use super::{decode, decode_any, render, LARGE, SMALL};
+
+    fn screen_from(rows: &[&str]) -> Screen {
+        let width = rows[0].len();
+        Screen::new(
+            width,
+            rows.iter()
+                .flat_map(|row| row.chars().map(|c| c == '#'))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn decode_small() {
+        let screen = screen_from(&[
+            "###...##..#..#..##..####.###..####.####.",
+            "#..#.#..#.#.#..#..#.#....#..#.#.......#.",
+            "###..#....##...#..#.###..#..#.###....#..",
+            "#..#.#.##.#.#..####.#....###..#.....#...",
+            "#..#.#..#.#.#..#..#.#....#.#..#....#....",
+            "###...###.#..#.#..#.####.#..#.####.####.",
+        ]);
+
+        assert_eq!(decode(&screen, &SMALL), "BGKAEREZ");
+    }
+
+    #[test]
+    fn unknown_letters() {
+        let screen = screen_from(&[
+            "##..##..", "##..##..", "##..##..", "##..##..", "##..##..", "##..##..",
+        ]);
+
+        assert_eq!(decode(&screen, &SMALL), "?");
+    }
+
+    #[test]
+    fn all_letters_round_trip() {
+        for font in [&SMALL, &LARGE] {
+            let text = font.glyphs.iter().map(|(c, _)| *c).collect::<String>();
+            let screen = render(&text, font, text.len() * font.pitch).unwrap();
+            assert_eq!(decode_any(&screen), Some(text));
+        }
+    }
+
+    #[test]
+    fn render_fit() {
+        assert!(render("BGKAEREZ", &SMALL, 40).is_some());
+        assert!(render("BGKAEREZA", &SMALL, 40).is_none());
+        assert!(render("b", &SMALL, 40).is_none());
+        assert_eq!(
+            render("HI", &SMALL, 10).unwrap().to_string(),
+            "#  #  ### \n#  #   #  \n####   #  \n#  #   #  \n#  #   #  \n#  #  ### "
+        );
+    }
+}
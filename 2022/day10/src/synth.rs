@@ -0,0 +1,198 @@
+use crate::crt::{Screen, WIDTH};
+use crate::machine::Instruction;
+use crate::ocr;
+
+// Sprite positions further off-screen than this are all alike: every pixel is dark
+const MIN_X: i64 = -2;
+const MAX_X: i64 = WIDTH as i64 + 1;
+
+#[derive(Debug, Clone, Copy)]
+enum Step {
+    Noop,
+    Addx(i64),
+}
+
+// Fewest instructions needed to reach a state, along with the previous state and the step that
+// got us there
+#[derive(Debug, Clone, Copy)]
+struct Best {
+    count: usize,
+    previous: (usize, i64),
+    step: Step,
+}
+
+// Finds the shortest `noop`/`addx` program that draws exactly `target`. The beam draws pixel `i`
+// during cycle `i + 1`, where it's lit iff the sprite (x-1..=x+1) covers its column, and x only
+// changes when an `addx` finishes. That makes it a shortest path over (pixel, x) states.
+//
+// Not every image can be drawn: x starts at 1 and can't change during the first two cycles, so
+// the first two pixels are always lit. Returns `None` for those, or if the screen isn't 40 wide.
+pub fn synthesise(target: &Screen) -> Option<Vec<Instruction>> {
+    if target.width() != WIDTH {
+        return None;
+    }
+
+    let pixels = target.width() * target.height();
+    let states = (MAX_X - MIN_X + 1) as usize;
+    let slot = |x: i64| (x - MIN_X) as usize;
+
+    let drawable = |i: usize, x: i64| {
+        let column = (i % WIDTH) as i64;
+        target.lit(i % WIDTH, i / WIDTH) == ((x - column).abs() <= 1)
+    };
+
+    // best[i][x]: how to start drawing pixel i with the sprite at x
+    let mut best: Vec<Vec<Option<Best>>> = vec![vec![None; states]; pixels + 1];
+    best[0][slot(1)] = Some(Best {
+        count: 0,
+        previous: (0, 1),
+        step: Step::Noop,
+    });
+
+    for i in 0..pixels {
+        for x in MIN_X..=MAX_X {
+            let Some(Best { count, .. }) = best[i][slot(x)] else {
+                continue;
+            };
+            if !drawable(i, x) {
+                continue;
+            }
+
+            let mut relax = |next: usize, next_x: i64, step: Step| {
+                let entry = &mut best[next][slot(next_x)];
+                if entry.is_none_or(|b| b.count > count + 1) {
+                    *entry = Some(Best {
+                        count: count + 1,
+                        previous: (i, x),
+                        step,
+                    });
+                }
+            };
+
+            relax(i + 1, x, Step::Noop);
+
+            if i + 1 < pixels && drawable(i + 1, x) {
+                // The value of the last addx never gets drawn, so don't bother moving the sprite
+                let targets = if i + 2 == pixels {
+                    x..=x
+                } else {
+                    MIN_X..=MAX_X
+                };
+                for next_x in targets {
+                    relax(i + 2, next_x, Step::Addx(next_x - x));
+                }
+            }
+        }
+    }
+
+    let (mut i, mut x) = (pixels, {
+        let end = &best[pixels];
+        let (slot, _) = end
+            .iter()
+            .enumerate()
+            .filter_map(|(s, entry)| Some((s, entry.as_ref()?.count)))
+            .min_by_key(|(_, count)| *count)?;
+        MIN_X + slot as i64
+    });
+
+    let mut program = vec![];
+    while i > 0 {
+        let Best { previous, step, .. } = best[i][slot(x)]?;
+        program.push(match step {
+            Step::Noop => Instruction::noop(),
+            Step::Addx(v) => Instruction::addx(v),
+        });
+        (i, x) = previous;
+    }
+    program.reverse();
+
+    Some(program)
+}
+
+pub fn synthesise_text(text: &str) -> Option<Vec<Instruction>> {
+    synthesise(&ocr::render(text, &ocr::SMALL, WIDTH)?)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::crt::Screen;
+    use crate::debugger::Debugger;
+    use crate::machine::Instruction;
+    use crate::ocr;
+
+    use super::{synthesise, synthesise_text};
+
+    fn draw(program: &[Instruction]) -> Screen {
+        Screen::from_cycles(Debugger::new(program))
+    }
+
+    // A random mix of noops and addx filling all 240 cycles, with the sprite anywhere from just
+    // off the left edge to just off the right one
+    fn random_program(mut seed: u64) -> Vec<Instruction> {
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+
+        let mut program = vec![];
+        let mut cycles = 0;
+        let mut x = 1i64;
+        while cycles < 240 {
+            if cycles + 2 <= 240 && next() % 3 != 0 {
+                let target = (next() % 44) as i64 - 2;
+                program.push(Instruction::addx(target - x));
+                x = target;
+                cycles += 2;
+            } else {
+                program.push(Instruction::noop());
+                cycles += 1;
+            }
+        }
+        program
+    }
+
+    #[test]
+    fn draws_text() {
+        let program = synthesise_text("BGKAEREZ").unwrap();
+
+        let screen = draw(&program);
+
+        assert_eq!(screen.height(), 6);
+        assert_eq!(ocr::decode(&screen, &ocr::SMALL), "BGKAEREZ");
+    }
+
+    #[test]
+    fn draws_puzzle_output() {
+        let program = crate::assembler::assemble(
+            &crate::machine::InstructionSet::day10(),
+            include_str!("../input.txt"),
+        )
+        .unwrap();
+        let target = draw(&program);
+
+        let synthesised = synthesise(&target).unwrap();
+
+        assert_eq!(draw(&synthesised), target);
+        assert!(synthesised.len() <= program.len());
+    }
+
+    #[test]
+    fn draws_random_screens() {
+        for seed in 1..=50 {
+            let target = draw(&random_program(seed));
+
+            let synthesised = synthesise(&target).unwrap();
+
+            assert_eq!(draw(&synthesised), target, "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn impossible_screens() {
+        assert_eq!(synthesise(&Screen::new(40, vec![false; 240])), None);
+        assert_eq!(synthesise(&Screen::new(20, vec![true; 120])), None);
+        assert_eq!(synthesise_text("ABC"), None);
+    }
+}
@@ -0,0 +1,52 @@
+use crate::monkey::{Monkey, TestOperation};
+
+// The throw graph in Graphviz format: a node per monkey showing its operation, a solid edge to
+// where divisible items go and a dashed one for the rest
+pub fn to_dot<W>(monkeys: &[Monkey<W>]) -> String {
+    let mut out = String::from("digraph monkeys {\n");
+
+    for monkey in monkeys {
+        let TestOperation::Divisible(divisor) = monkey.test.operation;
+        out += &format!(
+            "    {0} [label=\"Monkey {0}\\nnew = {1}\"];\n",
+            monkey.id, monkey.operation
+        );
+        out += &format!(
+            "    {} -> {} [label=\"divisible by {}\"];\n",
+            monkey.id,
+            monkey.test.on_true.id(),
+            divisor
+        );
+        out += &format!(
+            "    {} -> {} [label=\"not divisible by {}\", style=dashed];\n",
+            monkey.id,
+            monkey.test.on_false.id(),
+            divisor
+        );
+    }
+
+    out + "}\n"
+}
+
+#[cfg(test)]
+mod test {
+    use crate::monkey::{parse_monkeys, WorryTransformer};
+
+    use super::to_dot;
+
+    #[test]
+    fn example() {
+        let monkeys =
+            parse_monkeys(include_str!("../input-example.txt"), WorryTransformer::None).unwrap();
+
+        let dot = to_dot(&monkeys);
+
+        assert!(
+            dot.starts_with("digraph monkeys {\n    0 [label=\"Monkey 0\\nnew = old * 19\"];\n")
+        );
+        assert!(dot.contains("    0 -> 2 [label=\"divisible by 23\"];\n"));
+        assert!(dot.contains("    3 -> 1 [label=\"not divisible by 17\", style=dashed];\n"));
+        assert_eq!(dot.matches("->").count(), 8);
+        assert!(dot.ends_with("}\n"));
+    }
+}
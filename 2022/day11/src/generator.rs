@@ -0,0 +1,236 @@
+// Makes up monkey troops in the puzzle's format, reproducible from a seed
+
+use crate::monkey::{format_monkeys, Monkey, Test, WorryTransformer};
+use crate::operation::Operation;
+
+const PRIMES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Throws {
+    // Monkeys only throw to monkeys after them, except for the last one which throws everything
+    // to itself
+    Forward,
+    // Any monkey but itself
+    Cyclic,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    pub monkeys: usize,
+    pub max_items: usize,
+    // Distinct primes, like the puzzle, or any number from 2 to 40 with repeats
+    pub coprime: bool,
+    pub throws: Throws,
+    // Also operations that take something away. They keep worry levels above zero as long as
+    // they never get divided, so troops with them only suit relief by a common multiple.
+    pub subtract: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            monkeys: 4,
+            max_items: 4,
+            coprime: true,
+            throws: Throws::Cyclic,
+            subtract: false,
+        }
+    }
+}
+
+// The same seed has to give the same troop from one build to the next, so `--generate` output
+// can be shared and regenerated
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    fn between(&mut self, low: u64, high: u64) -> u64 {
+        low + self.below(high - low + 1)
+    }
+}
+
+fn add(a: Operation, b: Operation) -> Operation {
+    Operation::Add(Box::new(a), Box::new(b))
+}
+
+fn sub(a: Operation, b: Operation) -> Operation {
+    Operation::Sub(Box::new(a), Box::new(b))
+}
+
+fn mult(a: Operation, b: Operation) -> Operation {
+    Operation::Mult(Box::new(a), Box::new(b))
+}
+
+// At most one monkey squares its items, which is what makes worry levels blow up. Every
+// operation turns a worry level of at least 1 into one at least as big.
+fn operation(rng: &mut Rng, may_square: bool, subtract: bool) -> Operation {
+    use Operation::{Constant, Old};
+
+    let (a, b) = (rng.between(1, 9), rng.between(1, 9));
+    let kinds = if subtract { 7 } else { 5 };
+    match rng.below(kinds + u64::from(may_square)) {
+        0 => add(Old, Constant(a)),
+        1 => mult(Old, Constant(a + 1)),
+        2 => add(Old, Old),
+        3 => mult(add(Old, Constant(a)), Constant(b + 1)),
+        4 => add(Constant(a), mult(Old, Constant(b + 1))),
+        5 if subtract => sub(mult(Old, Constant(a + 1)), Constant(a)),
+        6 if subtract => add(mult(sub(Old, Constant(1)), Constant(b + 1)), Constant(a)),
+        _ => mult(Old, Old),
+    }
+}
+
+pub fn generate(seed: u64, options: &Options) -> String {
+    let mut rng = Rng::new(seed);
+    let n = options.monkeys.max(2);
+
+    let mut primes = PRIMES.to_vec();
+    let mut squared = false;
+    let mut monkeys = vec![];
+    for id in 0..n {
+        let items = (0..rng.between(1, options.max_items.max(1) as u64))
+            .map(|_| rng.between(1, 99))
+            .collect();
+
+        let operation = operation(&mut rng, !squared, options.subtract);
+        squared |= operation == mult(Operation::Old, Operation::Old);
+
+        let divisor = if options.coprime && !primes.is_empty() {
+            primes.remove(rng.below(primes.len() as u64) as usize)
+        } else {
+            rng.between(2, 40)
+        };
+
+        let target = |rng: &mut Rng| match options.throws {
+            Throws::Forward if id + 1 == n => id,
+            Throws::Forward => rng.between(id as u64 + 1, n as u64 - 1) as usize,
+            Throws::Cyclic => (id + 1 + rng.below(n as u64 - 1) as usize) % n,
+        };
+        let (on_true, on_false) = (target(&mut rng), target(&mut rng));
+
+        monkeys.push(Monkey::new(
+            id,
+            items,
+            operation,
+            Test::divisible(divisor, on_true, on_false),
+            WorryTransformer::None,
+        ));
+    }
+
+    format_monkeys(&monkeys)
+}
+
+#[cfg(test)]
+mod test {
+    use num_bigint::BigUint;
+
+    use crate::monkey::{divisors, format_monkeys, parse_monkeys, Monkey, WorryTransformer};
+    use crate::round::round;
+    use crate::worry::WorryNumber;
+
+    use super::{generate, Options, Throws};
+
+    fn all_options() -> Vec<Options> {
+        let mut options = vec![];
+        for coprime in [true, false] {
+            for throws in [Throws::Forward, Throws::Cyclic] {
+                for monkeys in [2, 4, 7] {
+                    for subtract in [false, true] {
+                        options.push(Options {
+                            monkeys,
+                            max_items: 5,
+                            coprime,
+                            throws,
+                            subtract,
+                        });
+                    }
+                }
+            }
+        }
+        options
+    }
+
+    fn inspected<W: WorryNumber>(mut monkeys: Vec<Monkey<W>>, rounds: usize) -> Vec<usize> {
+        for r in 1..=rounds {
+            round(&mut monkeys, r).unwrap();
+        }
+        monkeys.iter().map(|m| m.inspected).collect()
+    }
+
+    #[test]
+    fn reproducible_and_valid() {
+        for options in all_options() {
+            for seed in 1..=20 {
+                let troop = generate(seed, &options);
+                assert_eq!(troop, generate(seed, &options));
+
+                let monkeys = parse_monkeys(&troop, WorryTransformer::None).unwrap();
+                assert_eq!(monkeys.len(), options.monkeys);
+                assert_eq!(format_monkeys(&monkeys), troop);
+                if options.coprime {
+                    assert_eq!(divisors(&monkeys).len(), options.monkeys);
+                }
+                for monkey in &monkeys {
+                    let targets = [monkey.test.on_true.id(), monkey.test.on_false.id()];
+                    for target in targets {
+                        match options.throws {
+                            Throws::Forward => {
+                                assert!(target > monkey.id || target + 1 == options.monkeys)
+                            }
+                            Throws::Cyclic => assert_ne!(target, monkey.id),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn common_multiple_matches_unbounded_worry() {
+        for options in all_options() {
+            for seed in 1..=25 {
+                let troop = generate(seed, &options);
+                let rounds = 1 + (seed as usize % 10);
+
+                let unbounded = parse_monkeys(&troop, WorryTransformer::None)
+                    .unwrap()
+                    .into_iter()
+                    .map(|m| m.map_worry(BigUint::from))
+                    .collect();
+                let common_multiple =
+                    divisors(&parse_monkeys(&troop, WorryTransformer::None).unwrap())
+                        .iter()
+                        .product();
+                // Squares of anything below a u64 common multiple fit in a u128
+                let reduced = parse_monkeys(&troop, WorryTransformer::Rem(common_multiple))
+                    .unwrap()
+                    .into_iter()
+                    .map(|m| m.map_worry(u128::from))
+                    .collect();
+
+                assert_eq!(
+                    inspected(reduced, rounds),
+                    inspected(unbounded, rounds),
+                    "seed {} with {:?}:\n{}",
+                    seed,
+                    options,
+                    troop
+                );
+            }
+        }
+    }
+}
@@ -1,16 +1,98 @@
+mod dot;
+mod generator;
 mod monkey;
-use std::collections::HashMap;
+mod operation;
+mod rns;
+mod round;
+mod scenario;
+mod simulation;
+mod worry;
 use std::io::Read;
+use std::rc::Rc;
 
-use nom::character::complete::line_ending;
-use nom::multi::separated_list1;
+use num_bigint::BigUint;
 
-use self::monkey::{Monkey, Worry, WorryTransformer};
+use self::monkey::{divisors, format_monkeys, parse_monkeys, WorryTransformer};
+use self::rns::Residues;
+use self::round::{round, Events};
+use self::scenario::{Aggregation, Scenario};
+use self::worry::{WorryError, WorryNumber};
 
 fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    if let Some(seed) = arg_value(&args, "--generate") {
+        let options = generator::Options {
+            monkeys: arg_value(&args, "--monkeys").map_or(4, |n| n.parse().expect("monkeys")),
+            coprime: !args.iter().any(|a| a == "--any-divisors"),
+            throws: if args.iter().any(|a| a == "--forward") {
+                generator::Throws::Forward
+            } else {
+                generator::Throws::Cyclic
+            },
+            subtract: args.iter().any(|a| a == "--subtract"),
+            ..Default::default()
+        };
+        print!(
+            "{}",
+            generator::generate(seed.parse().expect("seed"), &options)
+        );
+        return;
+    }
+
     let mut buffer = vec![];
     std::io::stdin().read_to_end(&mut buffer).unwrap();
     let input = String::from_utf8(buffer).unwrap();
+    if let Err(e) = parse_monkeys(&input, WorryTransformer::None) {
+        eprintln!("invalid input: {}", e);
+        std::process::exit(1);
+    }
+
+    if args.iter().any(|a| a == "--dot" || a == "--format") {
+        let monkeys = parse_monkeys(&input, WorryTransformer::None).unwrap();
+        if args.iter().any(|a| a == "--dot") {
+            print!("{}", dot::to_dot(&monkeys));
+        } else {
+            print!("{}", format_monkeys(&monkeys));
+        }
+        return;
+    }
+    if let Some(worry) = arg_value(&args, "--worry") {
+        let rounds = arg_value(&args, "--rounds").map_or(10000, |r| r.parse().expect("rounds"));
+        let result = match worry {
+            "u64" => unreduced(&input, rounds, |w| w),
+            "u128" => unreduced(&input, rounds, u128::from),
+            "big" => unreduced(&input, rounds, BigUint::from),
+            "rns" => unreduced(&input, rounds, residues(&input)),
+            _ => panic!("--worry must be one of u64, u128, big or rns"),
+        };
+        match result {
+            Ok(business) => println!("monkey business after {} rounds: {}", rounds, business),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if let Some(rounds) = arg_value(&args, "--trace") {
+        trace(
+            &input,
+            rounds.parse().expect("rounds"),
+            arg_value(&args, "--item"),
+        );
+        return;
+    }
+    if let Some(scenario) = scenario(&args) {
+        match scenario.run(&input) {
+            Ok(business) => println!("{}", business),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
     let result_1 = part1(&input);
 
     println!("part1: {}", result_1);
@@ -19,75 +101,130 @@ fn main() {
     println!("part2: {}", result_2);
 }
 
-fn round(monkeys: &mut [monkey::Monkey]) {
-    let mut loose_items: HashMap<usize, Vec<Worry>> = HashMap::new();
-    for mut monkey in monkeys.iter_mut() {
-        let mut extra_items = loose_items.remove(&monkey.id).unwrap_or_default();
-
-        monkey.inventory.append(&mut extra_items);
-
-        let items = monkey.play_turn();
-        monkey.inspected += items.len();
+fn arg_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.windows(2)
+        .find(|pair| pair[0] == flag)
+        .map(|pair| pair[1].as_str())
+}
 
-        for (item, target_monkey) in items {
-            if let Some(mut items) = loose_items.remove(&target_monkey.id()) {
-                items.push(item);
-                loose_items.insert(target_monkey.id(), items);
-            } else {
-                loose_items.insert(target_monkey.id(), vec![item]);
-            }
-        }
+// `--scenario file.toml`, or part 2 with any of `--rounds`, `--relief`, `--top` and
+// `--aggregate` changed
+fn scenario(args: &[String]) -> Option<Scenario> {
+    if let Some(path) = arg_value(args, "--scenario") {
+        let source = std::fs::read_to_string(path).expect("readable scenario file");
+        return Some(Scenario::from_toml(&source).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }));
+    }
 
-        monkey.inventory = vec![];
+    let flags = ["--rounds", "--relief", "--top", "--aggregate"];
+    if !flags.iter().any(|flag| arg_value(args, flag).is_some()) {
+        return None;
     }
 
-    for (monkey, mut items) in loose_items.drain() {
-        monkeys[monkey].inventory.append(&mut items)
+    let mut scenario = Scenario::part2();
+    if let Some(rounds) = arg_value(args, "--rounds") {
+        scenario.rounds = rounds.parse().expect("rounds");
+    }
+    if let Some(relief) = arg_value(args, "--relief") {
+        scenario.relief = relief.parse().unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
     }
+    if let Some(top) = arg_value(args, "--top") {
+        scenario.top = top.parse().expect("top");
+    }
+    if let Some(aggregation) = arg_value(args, "--aggregate") {
+        scenario.aggregation =
+            Aggregation::parse(aggregation).expect("--aggregate must be product or sum");
+    }
+    Some(scenario)
 }
 
-fn part1(input: &str) -> i64 {
-    let (_, mut monkeys) = separated_list1(
-        line_ending,
-        Monkey::parse(WorryTransformer::FloorDiv(3u32.into())),
-    )(input)
-    .unwrap();
+// Prints every inspection in the first `rounds` rounds of part 1, optionally only for one item
+fn trace(input: &str, rounds: usize, item: Option<&str>) {
+    let item = item.map(|i| i.parse::<usize>().expect("item id"));
+    let monkeys = parse_monkeys(input, WorryTransformer::FloorDiv(3)).unwrap();
 
-    for _ in 1..=20 {
-        round(&mut monkeys);
+    let mut events = Events::new(monkeys, rounds);
+    let filtered = events.by_ref().filter(|e| match (e, item) {
+        (Ok(event), Some(id)) => event.item == id,
+        _ => true,
+    });
+    for event in filtered {
+        match event {
+            Ok(e) => println!(
+                "round {}: item {} monkey {} -> {} worry {}",
+                e.round, e.item, e.monkey, e.target, e.worry
+            ),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
     }
 
-    monkeys.sort_by(|m, m2| m2.inspected.cmp(&m.inspected));
-
-    // monkey business
-    monkeys[0].monkey_business(&monkeys[1]) as i64
+    for monkey in events.monkeys() {
+        println!(
+            "monkey {} inspected items {} times",
+            monkey.id, monkey.inspected
+        );
+    }
 }
 
-fn part2(input: &str) -> i64 {
-    let (_, monkeys) =
-        separated_list1(line_ending, Monkey::parse(WorryTransformer::None))(input).unwrap();
+#[derive(Debug, PartialEq, Eq)]
+enum UnreducedError {
+    TooFewMonkeys(usize),
+    // The round that failed
+    Round(usize, WorryError),
+}
 
-    let common_multiple = monkeys.iter().fold(1, |acc, m| {
-        acc * match m.test.operation {
-            monkey::TestOperation::Divisible(x) => x,
+impl std::fmt::Display for UnreducedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnreducedError::TooFewMonkeys(n) => write!(f, "only {} monkeys to pick from", n),
+            UnreducedError::Round(round, e) => write!(f, "round {}: {}", round, e),
         }
-    });
+    }
+}
 
-    let mut new_monkeys = monkeys
+// Plays without keeping worry levels in check, so only the representation limits how far it gets
+fn unreduced<W: WorryNumber>(
+    input: &str,
+    rounds: usize,
+    f: impl Fn(u64) -> W,
+) -> Result<usize, UnreducedError> {
+    let mut monkeys = parse_monkeys(input, WorryTransformer::None)
+        .unwrap()
         .into_iter()
-        .map(|mut m| {
-            m.worry_transformer = WorryTransformer::Rem(common_multiple);
-            m
-        })
+        .map(|m| m.map_worry(&f))
         .collect::<Vec<_>>();
+    if monkeys.len() < 2 {
+        return Err(UnreducedError::TooFewMonkeys(monkeys.len()));
+    }
 
-    for _ in 1..=10000 {
-        round(&mut new_monkeys);
+    for r in 1..=rounds {
+        round(&mut monkeys, r).map_err(|e| UnreducedError::Round(r, e))?;
     }
 
-    new_monkeys.sort_by(|m, m2| m2.inspected.cmp(&m.inspected));
+    monkeys.sort_by_key(|m| std::cmp::Reverse(m.inspected));
+    Ok(monkeys[0].monkey_business(&monkeys[1]))
+}
 
-    new_monkeys[0].monkey_business(&new_monkeys[1]) as i64
+// Builds residues modulo every divisor in the input
+fn residues(input: &str) -> impl Fn(u64) -> Residues {
+    let moduli: Rc<[u64]> = divisors(&parse_monkeys(input, WorryTransformer::None).unwrap()).into();
+    move |n| Residues::new(moduli.clone(), n)
+}
+
+fn part1(input: &str) -> i64 {
+    Scenario::part1().run(input).unwrap() as i64
+}
+
+fn part2(input: &str) -> i64 {
+    Scenario::part2().run(input).unwrap() as i64
 }
 
 #[cfg(test)]
@@ -103,4 +240,58 @@ mod test {
     fn part2() {
         assert_eq!(super::part2(INPUT), 2713310158);
     }
+
+    #[test]
+    fn unreduced() {
+        use super::worry::WorryError;
+        use super::UnreducedError;
+        use num_bigint::BigUint;
+
+        // Squaring items blows past u64 quickly, and past u128 not much later
+        let Err(UnreducedError::Round(failed, e)) = super::unreduced(INPUT, 20, |w| w) else {
+            panic!("u64 worry levels should overflow");
+        };
+        assert_eq!(e, WorryError::Overflow);
+        assert!(super::unreduced(INPUT, failed, u128::from).is_ok());
+        assert!(super::unreduced(INPUT, 40, u128::from).is_err());
+
+        // Nothing is lost when the numbers are allowed to grow: the same as part 2 for 20 rounds
+        assert_eq!(super::unreduced(INPUT, 20, BigUint::from), Ok(99 * 103));
+
+        // Only the first monkey, throwing everything to itself
+        let one = INPUT.split("\n\n").next().unwrap();
+        let one = one
+            .replace("monkey 2", "monkey 0")
+            .replace("monkey 3", "monkey 0");
+        assert_eq!(
+            super::unreduced(&one, 20, |w| w),
+            Err(UnreducedError::TooFewMonkeys(1))
+        );
+    }
+
+    #[test]
+    fn residues_match_common_multiple() {
+        for input in [INPUT, include_str!("../input.txt")] {
+            assert_eq!(
+                super::unreduced(input, 10000, super::residues(input)),
+                Ok(super::part2(input) as usize)
+            );
+        }
+    }
+
+    #[test]
+    fn residues_with_large_divisors() {
+        use num_bigint::BigUint;
+
+        // The product of these doesn't fit in a u64, so there's no common multiple to reduce by
+        let input = INPUT
+            .replace("divisible by 23", "divisible by 4294967291")
+            .replace("divisible by 19", "divisible by 4294967279")
+            .replace("divisible by 13", "divisible by 65521");
+
+        assert_eq!(
+            super::unreduced(&input, 15, super::residues(&input)),
+            super::unreduced(&input, 15, BigUint::from)
+        );
+    }
 }
@@ -1,83 +1,56 @@
-use std::ops::{Div, Rem};
+use std::fmt::Display;
 
+use nom::branch::alt;
 use nom::bytes::complete::tag;
-use nom::character::complete::{digit1, line_ending, space0};
-use nom::multi::separated_list1;
+use nom::character::complete::{digit1, line_ending, multispace0, space0};
+use nom::combinator::{all_consuming, consumed, cut, eof, map_res, not};
+use nom::multi::{separated_list0, separated_list1};
+use nom::sequence::terminated;
 use nom::IResult;
 
-// pub type Worry = num_bigint::BigUint;
-pub type Worry = u64;
-
-#[derive(Debug, PartialEq, Eq)]
-pub enum Operation {
-    Add(Worry),
-    Mult(Worry),
-    Square,
-    Double,
-    None,
-}
-
-impl Operation {
-    fn parse(input: &str) -> IResult<&str, Self> {
-        nom::combinator::map(
-            nom::sequence::tuple((
-                space0,
-                tag("Operation: "),
-                tag("new"),
-                tag(" = "),
-                nom::branch::alt((
-                    nom::sequence::tuple((tag("old + "), tag("old"))),
-                    nom::sequence::tuple((tag("old * "), tag("old"))),
-                    nom::sequence::tuple((tag("old + "), digit1)),
-                    nom::sequence::tuple((tag("old * "), digit1)),
-                )),
-            )),
-            |(_, _, _, _, op)| match op {
-                ("old + ", "old") => Self::Double,
-                ("old * ", "old") => Self::Square,
-                ("old + ", n) => Self::Add(n.parse().unwrap_or_default()),
-                ("old * ", n) => Self::Mult(n.parse().unwrap_or_default()),
-                _ => Self::None,
-            },
-        )(input)
-    }
-    fn execute(&self, target: Worry) -> Worry {
-        match self {
-            Operation::Add(x) => target + x,
-            Operation::Mult(x) => target * x,
-            Operation::Square => target.pow(2),
-            Operation::Double => target * 2u64,
-            Operation::None => target,
-        }
-    }
-}
+use crate::operation::Operation;
+use crate::worry::{WorryError, WorryNumber};
 
-#[cfg(test)]
-mod test_operation {
-    use super::Operation;
-
-    #[test]
-    fn parse() {
-        let input = "  Operation: new = old * 19\n";
+// How worry levels are written in the input
+pub type Worry = u64;
 
-        let operation = Operation::parse(input);
-        assert_eq!(operation, Ok(("\n", Operation::Mult(19u64))))
-    }
+fn number<T: std::str::FromStr>(input: &str) -> IResult<&str, T> {
+    map_res(digit1, str::parse)(input)
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum WorryTransformer {
     FloorDiv(Worry),
     Rem(Worry),
     None,
+    // Applies the first one, then the second
+    Then(Box<WorryTransformer>, Box<WorryTransformer>),
+    // Any expression of `old`, same as a monkey's operation
+    Custom(Operation),
 }
 
 impl WorryTransformer {
-    fn execute(&self, worry: Worry) -> Worry {
+    // The modulus worry levels are left reduced by, if any. Only their value modulo it matters
+    // then, which is what lets subtraction wrap around instead of failing.
+    pub fn modulus(&self) -> Option<Worry> {
         match self {
-            WorryTransformer::FloorDiv(n) => worry.div(n),
-            WorryTransformer::None => worry,
-            WorryTransformer::Rem(n) => worry.rem(n),
+            WorryTransformer::Rem(n) => Some(*n),
+            WorryTransformer::Then(first, second) => second.modulus().or_else(|| first.modulus()),
+            WorryTransformer::FloorDiv(_)
+            | WorryTransformer::None
+            | WorryTransformer::Custom(_) => None,
+        }
+    }
+
+    fn execute<W: WorryNumber>(&self, worry: W, modulus: Option<Worry>) -> Result<W, WorryError> {
+        match self {
+            WorryTransformer::FloorDiv(n) => worry.div(*n),
+            WorryTransformer::None => Ok(worry),
+            WorryTransformer::Rem(n) => worry.rem(*n),
+            WorryTransformer::Then(first, second) => {
+                second.execute(first.execute(worry, modulus)?, modulus)
+            }
+            WorryTransformer::Custom(operation) => operation.execute(&worry, modulus),
         }
     }
 }
@@ -108,23 +81,40 @@ impl Test {
             nom::sequence::tuple((
                 space0::<&str, _>,
                 tag("Test: divisible by "),
-                digit1,
+                number,
                 line_ending,
                 space0,
                 tag("If true: throw to monkey "),
-                digit1,
+                number,
                 line_ending,
                 space0,
                 tag("If false: throw to monkey "),
-                digit1,
+                number,
             )),
-            |(_, _, divisible, _, _, _, true_monkey, _, _, _, false_monkey)| Test {
-                operation: TestOperation::Divisible(divisible.parse().unwrap_or_default()),
-                on_true: Target::Monkey(true_monkey.parse().unwrap_or_default()),
-                on_false: Target::Monkey(false_monkey.parse().unwrap_or_default()),
+            |(_, _, divisible, _, _, _, true_monkey, _, _, _, false_monkey)| {
+                Test::divisible(divisible, true_monkey, false_monkey)
             },
         )(input)
     }
+
+    // Throws to `on_true` when the new worry level is divisible by `divisor`, else to `on_false`
+    pub fn divisible(divisor: Worry, on_true: usize, on_false: usize) -> Self {
+        Test {
+            operation: TestOperation::Divisible(divisor),
+            on_true: Target::Monkey(on_true),
+            on_false: Target::Monkey(on_false),
+        }
+    }
+}
+
+impl Display for Test {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.operation {
+            TestOperation::Divisible(x) => writeln!(f, "  Test: divisible by {}", x)?,
+        }
+        writeln!(f, "    If true: throw to monkey {}", self.on_true.id())?;
+        write!(f, "    If false: throw to monkey {}", self.on_false.id())
+    }
 }
 
 #[derive(Debug)]
@@ -133,74 +123,375 @@ pub enum TestOperation {
 }
 
 impl TestOperation {
-    fn execute(&self, target: &Worry) -> bool {
+    fn execute<W: WorryNumber>(&self, target: &W) -> Result<bool, WorryError> {
         match self {
-            TestOperation::Divisible(x) => target % x == 0u64,
+            TestOperation::Divisible(x) => target.divisible_by(*x),
         }
     }
 }
 
+// Items keep their id as they're thrown around. Ids follow the order items appear in the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Item<W = Worry> {
+    pub id: usize,
+    pub worry: W,
+}
+
 #[derive(Debug)]
-pub struct Monkey {
+pub struct Monkey<W = Worry> {
     pub id: usize,
     pub inspected: usize,
-    pub inventory: Vec<Worry>,
+    pub inventory: Vec<Item<W>>,
     pub operation: Operation,
     pub test: Test,
     pub worry_transformer: WorryTransformer,
 }
 
 impl Monkey {
+    // A monkey that hasn't inspected anything yet. Its items all get id 0 until `parse_monkeys`
+    // numbers them across the troop.
+    pub fn new(
+        id: usize,
+        worries: Vec<Worry>,
+        operation: Operation,
+        test: Test,
+        worry_transformer: WorryTransformer,
+    ) -> Self {
+        Self {
+            id,
+            inspected: 0,
+            inventory: worries
+                .into_iter()
+                .map(|worry| Item { id: 0, worry })
+                .collect(),
+            operation,
+            test,
+            worry_transformer,
+        }
+    }
+
     pub fn parse(transformer: WorryTransformer) -> impl FnMut(&str) -> IResult<&str, Self> {
         move |input: &str| {
             nom::combinator::map(
                 nom::sequence::tuple((
                     tag("Monkey "),
-                    digit1,
+                    number,
                     tag(":"),
                     line_ending,
                     space0,
-                    tag("Starting items: "),
-                    separated_list1(tag(", "), digit1),
+                    tag("Starting items:"),
+                    space0,
+                    separated_list0(tag(", "), number),
                     line_ending,
                     Operation::parse,
                     line_ending,
                     Test::parse,
-                    line_ending,
+                    alt((line_ending, eof)),
                 )),
-                |(_, id, _, _, _, _, inventory, _, op, _, test, _)| Self {
-                    id: id.parse().unwrap_or_default(),
-                    inspected: 0,
-                    inventory: inventory
-                        .into_iter()
-                        .filter_map(|i| i.parse::<Worry>().ok())
-                        .collect(),
-                    operation: op,
-                    worry_transformer: transformer,
-                    test,
+                |(_, id, _, _, _, _, _, inventory, _, op, _, test, _)| {
+                    Self::new(id, inventory, op, test, transformer.clone())
                 },
             )(input)
         }
     }
 
-    pub fn play_turn(&mut self) -> Vec<(Worry, Target)> {
-        self.inventory
-            .drain(0..)
-            .map(|item_worry| {
-                let new_worry = self
-                    .worry_transformer
-                    .execute(self.operation.execute(item_worry));
-                let target = if self.test.operation.execute(&new_worry) {
-                    self.test.on_true
-                } else {
-                    self.test.on_false
-                };
-                (new_worry, target)
+    // Switches to another worry representation, converting the starting items with `f`
+    pub fn map_worry<W: WorryNumber>(self, mut f: impl FnMut(Worry) -> W) -> Monkey<W> {
+        Monkey {
+            id: self.id,
+            inspected: self.inspected,
+            inventory: self
+                .inventory
+                .into_iter()
+                .map(|item| Item {
+                    id: item.id,
+                    worry: f(item.worry),
+                })
+                .collect(),
+            operation: self.operation,
+            test: self.test,
+            worry_transformer: self.worry_transformer,
+        }
+    }
+}
+
+impl<W: WorryNumber> Monkey<W> {
+    // What happens to a single item when this monkey inspects it
+    pub fn inspect(&self, item_worry: &W) -> Result<(W, Target), WorryError> {
+        let modulus = self.worry_transformer.modulus();
+        let new_worry = self
+            .worry_transformer
+            .execute(self.operation.execute(item_worry, modulus)?, modulus)?;
+        let target = if self.test.operation.execute(&new_worry)? {
+            self.test.on_true
+        } else {
+            self.test.on_false
+        };
+        Ok((new_worry, target))
+    }
+
+    pub fn play_turn(&mut self) -> Result<Vec<(Item<W>, Target)>, WorryError> {
+        let items = std::mem::take(&mut self.inventory);
+        items
+            .into_iter()
+            .map(|item| {
+                let (worry, target) = self.inspect(&item.worry)?;
+                Ok((Item { id: item.id, worry }, target))
             })
             .collect()
     }
 
-    pub fn monkey_business(&self, other: &Monkey) -> usize {
+    pub fn monkey_business(&self, other: &Monkey<W>) -> usize {
         self.inspected * other.inspected
     }
 }
+
+// The block this monkey was parsed from, without the final line break
+impl<W: Display> Display for Monkey<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let items = self
+            .inventory
+            .iter()
+            .map(|item| item.worry.to_string())
+            .collect::<Vec<_>>();
+
+        writeln!(f, "Monkey {}:", self.id)?;
+        if items.is_empty() {
+            writeln!(f, "  Starting items:")?;
+        } else {
+            writeln!(f, "  Starting items: {}", items.join(", "))?;
+        }
+        writeln!(f, "  Operation: new = {}", self.operation)?;
+        write!(f, "{}", self.test)
+    }
+}
+
+// Writes the monkeys the way the puzzle input lists them
+pub fn format_monkeys<W: Display>(monkeys: &[Monkey<W>]) -> String {
+    monkeys
+        .iter()
+        .map(|m| format!("{}\n", m))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Every divisor the monkeys test against, without repeats
+pub fn divisors<W>(monkeys: &[Monkey<W>]) -> Vec<Worry> {
+    let mut divisors = monkeys
+        .iter()
+        .map(|m| match m.test.operation {
+            TestOperation::Divisible(x) => x,
+        })
+        .collect::<Vec<_>>();
+    divisors.sort_unstable();
+    divisors.dedup();
+    divisors
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub near: String,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {}: unexpected input near {:?}",
+            self.line, self.near
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// Parses the whole input, failing on anything that isn't understood instead of stopping early
+pub fn parse_monkeys(
+    input: &str,
+    transformer: WorryTransformer,
+) -> Result<Vec<Monkey>, ParseError> {
+    // Whatever follows a blank line that isn't just more whitespace has to be a monkey, so a
+    // malformed one is reported where it goes wrong rather than at its header
+    let separator = terminated(line_ending, not(all_consuming(multispace0)));
+    let result = all_consuming(terminated(
+        separated_list1(separator, cut(consumed(Monkey::parse(transformer)))),
+        multispace0,
+    ))(input);
+
+    match result {
+        Ok((_, blocks)) => {
+            // Monkeys are looked up by their position, so ids have to count up from 0 and every
+            // throw has to land on one of them. Nothing is divisible by 0, and worry levels get
+            // reduced modulo the divisors.
+            for (position, (block, monkey)) in blocks.iter().enumerate() {
+                let targets = [monkey.test.on_true, monkey.test.on_false];
+                let invalid = if monkey.id != position {
+                    Some(0)
+                } else if matches!(monkey.test.operation, TestOperation::Divisible(0)) {
+                    Some(3)
+                } else {
+                    // The header, items, operation and test come before the targets
+                    (4..)
+                        .zip(targets)
+                        .find(|(_, target)| target.id() >= blocks.len())
+                        .map(|(line, _)| line)
+                };
+                if let Some(line) = invalid {
+                    let start = block.as_ptr() as usize - input.as_ptr() as usize;
+                    return Err(ParseError {
+                        line: input[..start].matches('\n').count() + line + 1,
+                        near: block
+                            .lines()
+                            .nth(line)
+                            .unwrap_or_default()
+                            .trim()
+                            .to_string(),
+                    });
+                }
+            }
+
+            let mut monkeys = blocks
+                .into_iter()
+                .map(|(_, monkey)| monkey)
+                .collect::<Vec<_>>();
+            let items = monkeys.iter_mut().flat_map(|m| m.inventory.iter_mut());
+            for (id, item) in items.enumerate() {
+                item.id = id;
+            }
+            Ok(monkeys)
+        }
+        Err(nom::Err::Error(e) | nom::Err::Failure(e)) => {
+            let consumed = &input[..input.len() - e.input.len()];
+            Err(ParseError {
+                line: consumed.matches('\n').count() + 1,
+                near: e.input.lines().next().unwrap_or_default().to_string(),
+            })
+        }
+        Err(nom::Err::Incomplete(_)) => unreachable!("complete parsers never ask for more input"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{format_monkeys, parse_monkeys, Item, WorryTransformer};
+
+    const MONKEY: &str = "Monkey 0:
+  Starting items: 79, 98
+  Operation: new = (old + 3) * 2 - old
+  Test: divisible by 23
+    If true: throw to monkey 0
+    If false: throw to monkey 0";
+
+    #[test]
+    fn parses_expressions() {
+        let monkeys = parse_monkeys(MONKEY, WorryTransformer::None).unwrap();
+
+        assert_eq!(monkeys.len(), 1);
+        assert_eq!(
+            monkeys[0].inventory,
+            vec![Item { id: 0, worry: 79 }, Item { id: 1, worry: 98 }]
+        );
+        assert_eq!(monkeys[0].operation.execute(&10u64, None), Ok(16));
+    }
+
+    #[test]
+    fn formats_back_to_the_input() {
+        for input in [
+            include_str!("../input-example.txt"),
+            include_str!("../input.txt"),
+        ] {
+            let monkeys = parse_monkeys(input, WorryTransformer::None).unwrap();
+
+            assert_eq!(format_monkeys(&monkeys), input);
+        }
+
+        let mut monkeys = parse_monkeys(MONKEY, WorryTransformer::None).unwrap();
+        monkeys[0].inventory.clear();
+        let formatted = format_monkeys(&monkeys);
+        assert!(formatted.contains("  Starting items:\n"));
+        let reparsed = parse_monkeys(&formatted, WorryTransformer::None).unwrap();
+        assert_eq!(format_monkeys(&reparsed), formatted);
+    }
+
+    #[test]
+    fn rejects_unsupported_operations() {
+        let input = MONKEY.replace("(old + 3) * 2 - old", "old / 2");
+
+        let error = parse_monkeys(&input, WorryTransformer::None).unwrap_err();
+
+        assert_eq!(error.line, 3);
+    }
+
+    #[test]
+    fn rejects_unsupported_operations_in_later_monkeys() {
+        let second = MONKEY
+            .replace("Monkey 0", "Monkey 1")
+            .replace("(old + 3) * 2 - old", "old / 2");
+        let input = format!("{}\n\n{}", MONKEY, second);
+
+        let error = parse_monkeys(&input, WorryTransformer::None).unwrap_err();
+
+        assert_eq!(error.line, 10);
+        assert!(error.near.ends_with("/ 2"), "{:?}", error.near);
+    }
+
+    #[test]
+    fn rejects_unknown_monkeys() {
+        let second = MONKEY.replace("Monkey 0", "Monkey 1");
+        let input = format!("{}\n\n{}", MONKEY, second);
+        assert_eq!(
+            parse_monkeys(&input, WorryTransformer::None).unwrap().len(),
+            2
+        );
+
+        let input = format!(
+            "{}\n\n{}",
+            MONKEY.replace("monkey 0\n", "monkey 2\n"),
+            second
+        );
+        let error = parse_monkeys(&input, WorryTransformer::None).unwrap_err();
+        assert_eq!(
+            (error.line, error.near.as_str()),
+            (5, "If true: throw to monkey 2")
+        );
+
+        let input = format!(
+            "{}\n\n{}",
+            MONKEY,
+            second.replace("false: throw to monkey 0", "false: throw to monkey 3")
+        );
+        let error = parse_monkeys(&input, WorryTransformer::None).unwrap_err();
+        assert_eq!(
+            (error.line, error.near.as_str()),
+            (13, "If false: throw to monkey 3")
+        );
+
+        // Monkeys are numbered in order
+        let input = format!("{}\n\n{}", MONKEY, MONKEY.replace("Monkey 0", "Monkey 2"));
+        let error = parse_monkeys(&input, WorryTransformer::None).unwrap_err();
+        assert_eq!((error.line, error.near.as_str()), (8, "Monkey 2:"));
+    }
+
+    #[test]
+    fn rejects_zero_divisors() {
+        let second = MONKEY
+            .replace("Monkey 0", "Monkey 1")
+            .replace("divisible by 23", "divisible by 0");
+        let input = format!("{}\n\n{}", MONKEY, second);
+
+        let error = parse_monkeys(&input, WorryTransformer::None).unwrap_err();
+
+        assert_eq!(
+            (error.line, error.near.as_str()),
+            (11, "Test: divisible by 0")
+        );
+    }
+
+    #[test]
+    fn rejects_trailing_garbage() {
+        let input = format!("{}\n\nMonkey 1:\n", MONKEY);
+
+        assert!(parse_monkeys(&input, WorryTransformer::None).is_err());
+    }
+}
@@ -0,0 +1,235 @@
+use nom::branch::alt;
+use nom::bytes::complete::tag;
+use nom::character::complete::{digit1, one_of, space0};
+use nom::combinator::{map, map_res};
+use nom::multi::fold_many0;
+use nom::sequence::{delimited, pair, preceded};
+use nom::IResult;
+
+use crate::monkey::Worry;
+use crate::worry::{WorryError, WorryNumber};
+
+// The right hand side of `new = ...`, parsed with the usual precedence: `*` binds tighter than
+// `+` and `-`, both are left associative, and parentheses group.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Operation {
+    Old,
+    Constant(Worry),
+    Add(Box<Operation>, Box<Operation>),
+    Sub(Box<Operation>, Box<Operation>),
+    Mult(Box<Operation>, Box<Operation>),
+}
+
+impl Operation {
+    pub fn parse(input: &str) -> IResult<&str, Self> {
+        preceded(
+            nom::sequence::tuple((space0, tag("Operation: "), tag("new"), tag(" = "))),
+            Self::parse_expression,
+        )(input)
+    }
+
+    fn parse_factor(input: &str) -> IResult<&str, Self> {
+        delimited(
+            space0,
+            alt((
+                map(tag("old"), |_| Self::Old),
+                map_res(digit1, |n: &str| n.parse().map(Self::Constant)),
+                delimited(tag("("), Self::parse_expression, tag(")")),
+            )),
+            space0,
+        )(input)
+    }
+
+    fn parse_term(input: &str) -> IResult<&str, Self> {
+        let (input, first) = Self::parse_factor(input)?;
+        fold_many0(
+            preceded(tag("*"), Self::parse_factor),
+            move || first.clone(),
+            |acc, factor| Self::Mult(Box::new(acc), Box::new(factor)),
+        )(input)
+    }
+
+    pub fn parse_expression(input: &str) -> IResult<&str, Self> {
+        let (input, first) = Self::parse_term(input)?;
+        fold_many0(
+            pair(one_of("+-"), Self::parse_term),
+            move || first.clone(),
+            |acc, (op, term)| match op {
+                '+' => Self::Add(Box::new(acc), Box::new(term)),
+                _ => Self::Sub(Box::new(acc), Box::new(term)),
+            },
+        )(input)
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Operation::Old | Operation::Constant(_) => 2,
+            Operation::Mult(..) => 1,
+            Operation::Add(..) | Operation::Sub(..) => 0,
+        }
+    }
+
+    // `modulus` is set when worry levels are only known modulo it. A reduced `old` can be smaller
+    // than what's subtracted from it when the real worry level isn't, so subtraction wraps around
+    // the modulus then instead of going below zero.
+    pub fn execute<W: WorryNumber>(
+        &self,
+        old: &W,
+        modulus: Option<Worry>,
+    ) -> Result<W, WorryError> {
+        match self {
+            Operation::Old => Ok(old.clone()),
+            Operation::Constant(n) => Ok(old.lift(*n)),
+            Operation::Add(a, b) => a.execute(old, modulus)?.add(&b.execute(old, modulus)?),
+            Operation::Sub(a, b) => {
+                let (a, b) = (a.execute(old, modulus)?, b.execute(old, modulus)?);
+                match modulus {
+                    Some(m) => a.add(&a.lift(m))?.sub(&b.rem(m)?)?.rem(m),
+                    None => a.sub(&b),
+                }
+            }
+            Operation::Mult(a, b) => a.execute(old, modulus)?.mul(&b.execute(old, modulus)?),
+        }
+    }
+}
+
+// Writes `old` expressions back with spaces around operators and only the parentheses needed to
+// parse to the same tree again
+impl std::fmt::Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (a, op, b) = match self {
+            Operation::Old => return write!(f, "old"),
+            Operation::Constant(n) => return write!(f, "{}", n),
+            Operation::Add(a, b) => (a, "+", b),
+            Operation::Sub(a, b) => (a, "-", b),
+            Operation::Mult(a, b) => (a, "*", b),
+        };
+
+        if a.precedence() < self.precedence() {
+            write!(f, "({})", a)?;
+        } else {
+            write!(f, "{}", a)?;
+        }
+        write!(f, " {} ", op)?;
+        if b.precedence() <= self.precedence() {
+            write!(f, "({})", b)
+        } else {
+            write!(f, "{}", b)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_operation {
+    use super::Operation;
+    use super::Operation::{Add, Constant, Mult, Old, Sub};
+    use crate::worry::WorryError;
+
+    fn expression(input: &str) -> Operation {
+        let (rest, operation) = Operation::parse_expression(input).unwrap();
+        assert_eq!(rest, "", "unparsed input for {}", input);
+        operation
+    }
+
+    #[test]
+    fn parse() {
+        let input = "  Operation: new = old * 19\n";
+
+        let operation = Operation::parse(input);
+        assert_eq!(
+            operation,
+            Ok(("\n", Mult(Box::new(Old), Box::new(Constant(19)))))
+        )
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(
+            expression("2 + old * 3 - 1"),
+            Sub(
+                Box::new(Add(
+                    Box::new(Constant(2)),
+                    Box::new(Mult(Box::new(Old), Box::new(Constant(3))))
+                )),
+                Box::new(Constant(1))
+            )
+        );
+        assert_eq!(
+            expression("(old + 1) * (old - 1)"),
+            Mult(
+                Box::new(Add(Box::new(Old), Box::new(Constant(1)))),
+                Box::new(Sub(Box::new(Old), Box::new(Constant(1))))
+            )
+        );
+    }
+
+    #[test]
+    fn execute() {
+        assert_eq!(expression("old * old").execute(&7u64, None), Ok(49));
+        assert_eq!(expression("3 + old").execute(&7u64, None), Ok(10));
+        assert_eq!(expression("old - 2 - 3").execute(&7u64, None), Ok(2));
+        assert_eq!(
+            expression("(old + 1) * (old - 1)").execute(&7u64, None),
+            Ok(48)
+        );
+        assert_eq!(expression("( old+1 )*2").execute(&7u64, None), Ok(16));
+        assert_eq!(
+            expression("old - 8").execute(&7u64, None),
+            Err(WorryError::Underflow)
+        );
+        assert_eq!(
+            expression("old * old").execute(&u64::MAX, None),
+            Err(WorryError::Overflow)
+        );
+    }
+
+    #[test]
+    fn execute_modulo() {
+        // 7 stands for 7, 17, 27...: taking 8 away wraps around instead of going below zero
+        assert_eq!(expression("old - 8").execute(&7u64, Some(10)), Ok(9));
+        assert_eq!(expression("old * 10 - 25").execute(&2u64, Some(3)), Ok(1));
+        assert_eq!(
+            expression("old - 8").execute(&7u64, None),
+            Err(WorryError::Underflow)
+        );
+        assert_eq!(
+            expression("(old + 1) * (old - 1)").execute(&7u64, Some(100)),
+            Ok(48)
+        );
+    }
+
+    #[test]
+    fn display() {
+        for source in [
+            "old * 19",
+            "old * old",
+            "3 + old",
+            "2 + old * 3 - 1",
+            "(old + 1) * (old - 1)",
+            "old - (2 - 3)",
+            "old * (old * 2)",
+            "(old + 2) * 3 + 1",
+        ] {
+            let operation = expression(source);
+            assert_eq!(operation.to_string(), source);
+            assert_eq!(expression(&operation.to_string()), operation);
+        }
+
+        assert_eq!(expression("( old+1 )*2").to_string(), "(old + 1) * 2");
+        assert_eq!(expression("((old))").to_string(), "old");
+    }
+
+    #[test]
+    fn unsupported() {
+        assert_ne!(
+            Operation::parse_expression("old / 2").map(|(r, _)| r),
+            Ok("")
+        );
+        assert_ne!(
+            Operation::parse_expression("old ** 2").map(|(r, _)| r),
+            Ok("")
+        );
+        assert!(Operation::parse_expression("(old + 2").is_err());
+        assert!(Operation::parse_expression("- old").is_err());
+    }
+}
@@ -0,0 +1,131 @@
+use std::fmt::Display;
+use std::rc::Rc;
+
+use crate::worry::{WorryError, WorryNumber};
+
+// A worry level kept as its remainder modulo each monkey's divisor instead of as one number. Each
+// residue stays below its modulus, so nothing grows with the number of monkeys the way a common
+// multiple does, and `+`, `-` and `*` (including `old * old`) work residue by residue.
+//
+// The price is that the value itself is gone: dividing it, or asking about a divisor that isn't
+// one of the moduli, is `Unsupported`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Residues {
+    moduli: Rc<[u64]>,
+    values: Vec<u64>,
+}
+
+impl Residues {
+    pub fn new(moduli: Rc<[u64]>, n: u64) -> Self {
+        let values = moduli.iter().map(|m| n % m).collect();
+        Self { moduli, values }
+    }
+
+    fn zip_with(&self, other: &Self, f: impl Fn(u128, u128, u128) -> u128) -> Self {
+        let values = self
+            .moduli
+            .iter()
+            .zip(self.values.iter().zip(&other.values))
+            .map(|(&m, (&a, &b))| (f(a as u128, b as u128, m as u128) % m as u128) as u64)
+            .collect();
+        Self {
+            moduli: self.moduli.clone(),
+            values,
+        }
+    }
+}
+
+impl Display for Residues {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let residues = self
+            .moduli
+            .iter()
+            .zip(&self.values)
+            .map(|(m, v)| format!("{} mod {}", v, m))
+            .collect::<Vec<_>>();
+        write!(f, "[{}]", residues.join(", "))
+    }
+}
+
+impl WorryNumber for Residues {
+    fn lift(&self, n: u64) -> Self {
+        Self::new(self.moduli.clone(), n)
+    }
+
+    fn add(&self, other: &Self) -> Result<Self, WorryError> {
+        Ok(self.zip_with(other, |a, b, _| a + b))
+    }
+
+    // Only the value modulo every modulus is known, so there's no telling whether it went
+    // below zero
+    fn sub(&self, other: &Self) -> Result<Self, WorryError> {
+        Ok(self.zip_with(other, |a, b, m| a + m - b))
+    }
+
+    fn mul(&self, other: &Self) -> Result<Self, WorryError> {
+        Ok(self.zip_with(other, |a, b, _| a * b))
+    }
+
+    fn div(&self, _: u64) -> Result<Self, WorryError> {
+        Err(WorryError::Unsupported)
+    }
+
+    // Reducing modulo a multiple of every modulus doesn't change any residue
+    fn rem(&self, n: u64) -> Result<Self, WorryError> {
+        if self.moduli.iter().all(|m| n.is_multiple_of(*m)) {
+            Ok(self.clone())
+        } else {
+            Err(WorryError::Unsupported)
+        }
+    }
+
+    fn divisible_by(&self, n: u64) -> Result<bool, WorryError> {
+        self.moduli
+            .iter()
+            .zip(&self.values)
+            .find(|(m, _)| m.is_multiple_of(n))
+            .map(|(_, v)| v.is_multiple_of(n))
+            .ok_or(WorryError::Unsupported)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+
+    use super::Residues;
+    use crate::worry::{WorryError, WorryNumber};
+
+    #[test]
+    fn arithmetic() {
+        let moduli: Rc<[u64]> = Rc::from(vec![7, 11, 13]);
+        let a = Residues::new(moduli.clone(), 500);
+        let b = Residues::new(moduli.clone(), 123);
+
+        assert_eq!(a.add(&b), Ok(Residues::new(moduli.clone(), 623)));
+        assert_eq!(a.sub(&b), Ok(Residues::new(moduli.clone(), 377)));
+        assert_eq!(a.mul(&a), Ok(Residues::new(moduli.clone(), 250000)));
+        assert_eq!(a.divisible_by(11), Ok(false));
+        assert_eq!(a.add(&a.lift(6)).unwrap().divisible_by(11), Ok(true));
+    }
+
+    #[test]
+    fn large_moduli() {
+        let moduli: Rc<[u64]> = Rc::from(vec![u64::MAX - 58, u64::MAX - 82]);
+        let a = Residues::new(moduli, u64::MAX - 100);
+
+        let square = a.mul(&a).unwrap().mul(&a).unwrap();
+
+        assert_eq!(square.divisible_by(u64::MAX - 58), Ok(false));
+    }
+
+    #[test]
+    fn unsupported() {
+        let a = Residues::new(Rc::from(vec![2, 3]), 5);
+
+        assert_eq!(a.div(3), Err(WorryError::Unsupported));
+        assert_eq!(a.rem(4), Err(WorryError::Unsupported));
+        assert_eq!(a.rem(12), Ok(a.clone()));
+        assert_eq!(a.divisible_by(5), Err(WorryError::Unsupported));
+    }
+}
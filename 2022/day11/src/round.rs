@@ -0,0 +1,316 @@
+use std::collections::VecDeque;
+
+use crate::monkey::Monkey;
+use crate::worry::{WorryError, WorryNumber};
+
+// A monkey inspecting an item: who held it, what its worry became and where it was thrown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event<W> {
+    pub round: usize,
+    pub item: usize,
+    pub monkey: usize,
+    pub worry: W,
+    pub target: usize,
+}
+
+// Plays round number `number`, returning every inspection in the order they happened.
+//
+// Monkeys take turns in order. A thrown item lands at the back of the target's inventory right
+// away, so a monkey later in the order inspects it in this same round. A monkey only inspects the
+// items it held when its turn started: anything thrown to itself waits for the next round.
+pub fn round<W: WorryNumber>(
+    monkeys: &mut [Monkey<W>],
+    number: usize,
+) -> Result<Vec<Event<W>>, WorryError> {
+    let mut events = vec![];
+    for id in 0..monkeys.len() {
+        let items = monkeys[id].play_turn()?;
+        monkeys[id].inspected += items.len();
+
+        for (item, target) in items {
+            events.push(Event {
+                round: number,
+                item: item.id,
+                monkey: id,
+                worry: item.worry.clone(),
+                target: target.id(),
+            });
+            monkeys[target.id()].inventory.push(item);
+        }
+    }
+
+    Ok(events)
+}
+
+// Plays `rounds` rounds one at a time, yielding each inspection as it happens. Stops after the
+// first error.
+pub struct Events<W> {
+    monkeys: Vec<Monkey<W>>,
+    round: usize,
+    rounds: usize,
+    pending: VecDeque<Event<W>>,
+    failed: bool,
+}
+
+impl<W: WorryNumber> Events<W> {
+    pub fn new(monkeys: Vec<Monkey<W>>, rounds: usize) -> Self {
+        Self {
+            monkeys,
+            round: 0,
+            rounds,
+            pending: VecDeque::new(),
+            failed: false,
+        }
+    }
+
+    pub fn monkeys(&self) -> &[Monkey<W>] {
+        &self.monkeys
+    }
+}
+
+impl<W: WorryNumber> Iterator for Events<W> {
+    type Item = Result<Event<W>, WorryError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() {
+            if self.failed || self.round == self.rounds {
+                return None;
+            }
+            self.round += 1;
+            match round(&mut self.monkeys, self.round) {
+                Ok(events) => self.pending.extend(events),
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+
+        self.pending.pop_front().map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::monkey::{format_monkeys, parse_monkeys, Monkey, Test, Worry, WorryTransformer};
+    use crate::operation::Operation;
+
+    use super::{round, Event, Events};
+
+    const INPUT: &str = include_str!("../input-example.txt");
+
+    // Monkeys as (items, operation, divisor, if true, if false), in the puzzle's format
+    fn monkeys(spec: &[(&[Worry], &str, Worry, usize, usize)]) -> String {
+        let monkeys = spec
+            .iter()
+            .enumerate()
+            .map(|(id, &(items, operation, divisor, on_true, on_false))| {
+                Monkey::new(
+                    id,
+                    items.to_vec(),
+                    Operation::parse_expression(operation).unwrap().1,
+                    Test::divisible(divisor, on_true, on_false),
+                    WorryTransformer::None,
+                )
+            })
+            .collect::<Vec<_>>();
+        format_monkeys(&monkeys)
+    }
+
+    // (monkey, item) for every inspection in the first `rounds` rounds
+    fn inspections(input: &str, rounds: usize) -> Vec<(usize, usize)> {
+        let monkeys = parse_monkeys(input, WorryTransformer::None).unwrap();
+        Events::new(monkeys, rounds)
+            .map(|e| e.map(|e| (e.monkey, e.item)).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn first_round() {
+        let monkeys = parse_monkeys(INPUT, WorryTransformer::FloorDiv(3)).unwrap();
+
+        let events = Events::new(monkeys, 1)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        // Straight from the puzzle: item 79 goes 0 -> 3 at 500, then 3 -> 1 at 167
+        assert_eq!(
+            events[0],
+            Event {
+                round: 1,
+                item: 0,
+                monkey: 0,
+                worry: 500,
+                target: 3
+            }
+        );
+        let path = events
+            .iter()
+            .filter(|e| e.item == 0)
+            .map(|e| (e.monkey, e.worry, e.target))
+            .collect::<Vec<_>>();
+        assert_eq!(path, vec![(0, 500, 3), (3, 167, 1)]);
+        assert_eq!(events.len(), 2 + 4 + 3 + 5);
+    }
+
+    #[test]
+    fn items_keep_their_ids() {
+        let monkeys = parse_monkeys(INPUT, WorryTransformer::FloorDiv(3)).unwrap();
+        let mut events = Events::new(monkeys, 20);
+
+        let inspections = events
+            .by_ref()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap()
+            .len();
+
+        let mut ids = events
+            .monkeys()
+            .iter()
+            .flat_map(|m| m.inventory.iter().map(|i| i.id))
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        assert_eq!(ids, (0..10).collect::<Vec<_>>());
+        assert_eq!(
+            inspections,
+            events.monkeys().iter().map(|m| m.inspected).sum()
+        );
+    }
+
+    #[test]
+    fn forward_throws_are_inspected_the_same_round() {
+        // Everything goes 0 -> 1 -> 2 and back to 0
+        let input = monkeys(&[
+            (&[1, 2], "old", 1, 1, 1),
+            (&[3], "old", 1, 2, 2),
+            (&[4], "old", 1, 0, 0),
+        ]);
+
+        assert_eq!(
+            inspections(&input, 1),
+            vec![
+                (0, 0),
+                (0, 1),
+                (1, 2),
+                (1, 0),
+                (1, 1),
+                (2, 3),
+                (2, 2),
+                (2, 0),
+                (2, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn backward_throws_wait_for_the_next_round() {
+        // Everything goes 2 -> 1 -> 0 and back to 2, one hop per round
+        let input = monkeys(&[
+            (&[0], "old", 1, 2, 2),
+            (&[0], "old", 1, 0, 0),
+            (&[1], "old", 1, 1, 1),
+        ]);
+
+        let inspected = inspections(&input, 3)
+            .into_iter()
+            .filter(|(_, item)| *item == 2)
+            .map(|(monkey, _)| monkey)
+            .collect::<Vec<_>>();
+
+        assert_eq!(inspected, vec![2, 1, 0, 2]);
+    }
+
+    #[test]
+    fn throws_queue_in_order() {
+        // Monkey 2 already holds item 2 when 0 and 1 throw theirs, and 3 throws back to 0 after
+        // 1 did. Odd items go back to monkey 0, even ones stay put.
+        let input = monkeys(&[
+            (&[10], "old", 1, 2, 2),
+            (&[20], "old", 1, 2, 2),
+            (&[30], "old", 1, 0, 0),
+            (&[40, 41], "old", 2, 3, 0),
+        ]);
+
+        let mut monkeys = parse_monkeys(&input, WorryTransformer::None).unwrap();
+        round(&mut monkeys, 1).unwrap();
+
+        let held = |m: usize| {
+            monkeys[m]
+                .inventory
+                .iter()
+                .map(|i| i.id)
+                .collect::<Vec<_>>()
+        };
+        // 2 inspected its own item first, then what 0 and 1 threw, all going to 0, then 3 threw
+        // item 4 after keeping item 3 for itself
+        assert_eq!(held(0), vec![2, 0, 1, 4]);
+        assert_eq!(held(3), vec![3]);
+        assert_eq!(monkeys[3].inspected, 2);
+    }
+
+    #[test]
+    fn self_throws_wait_for_the_next_round() {
+        let input = monkeys(&[(&[1, 2, 3], "old + 1", 1, 0, 0)]);
+
+        let mut monkeys = parse_monkeys(&input, WorryTransformer::None).unwrap();
+        round(&mut monkeys, 1).unwrap();
+        round(&mut monkeys, 2).unwrap();
+
+        assert_eq!(monkeys[0].inspected, 6);
+        let worries = monkeys[0]
+            .inventory
+            .iter()
+            .map(|i| i.worry)
+            .collect::<Vec<_>>();
+        assert_eq!(worries, vec![3, 4, 5]);
+    }
+
+    // The inspection counts after `rounds` rounds where thrown items only reach their targets
+    // once every monkey has had its turn
+    fn batched(input: &str, rounds: usize) -> Vec<usize> {
+        let mut monkeys = parse_monkeys(input, WorryTransformer::None).unwrap();
+        for _ in 0..rounds {
+            let mut thrown = vec![];
+            for monkey in monkeys.iter_mut() {
+                let items = monkey.play_turn().unwrap();
+                monkey.inspected += items.len();
+                thrown.extend(items);
+            }
+            for (item, target) in thrown {
+                monkeys[target.id()].inventory.push(item);
+            }
+        }
+        monkeys.iter().map(|m| m.inspected).collect()
+    }
+
+    #[test]
+    fn delivery_order_changes_monkey_business() {
+        // Monkey 0 passes every item on to 1 or 2, which mostly send them back. Delivered straight
+        // away an item gets there in the same round, batched it waits a round at every hop.
+        let input = monkeys(&[
+            (&[1, 2, 3], "old * 3 + 1", 2, 1, 2),
+            (&[4], "old + 5", 3, 0, 2),
+            (&[], "old * 2", 5, 0, 0),
+        ]);
+
+        let mut immediate = parse_monkeys(&input, WorryTransformer::None).unwrap();
+        for r in 1..=4 {
+            round(&mut immediate, r).unwrap();
+        }
+        let immediate = immediate.iter().map(|m| m.inspected).collect::<Vec<_>>();
+
+        // Monkey business of 15 * 12 instead of 8 * 6
+        assert_eq!(immediate, vec![15, 12, 4]);
+        assert_eq!(batched(&input, 4), vec![8, 6, 2]);
+    }
+
+    #[test]
+    fn stops_on_errors() {
+        let monkeys = parse_monkeys(INPUT, WorryTransformer::None).unwrap();
+
+        let events = Events::new(monkeys, 100).collect::<Vec<_>>();
+
+        assert!(events.last().unwrap().is_err());
+        assert_eq!(events.iter().filter(|e| e.is_err()).count(), 1);
+    }
+}
@@ -0,0 +1,377 @@
+use std::fmt::Display;
+
+use nom::combinator::all_consuming;
+use serde::Deserialize;
+
+use crate::monkey::{divisors, parse_monkeys, ParseError, Worry, WorryTransformer};
+use crate::operation::Operation;
+use crate::simulation::Simulator;
+use crate::worry::WorryError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Aggregation {
+    Product,
+    Sum,
+}
+
+impl Aggregation {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "product" => Some(Self::Product),
+            "sum" => Some(Self::Sum),
+            _ => None,
+        }
+    }
+
+    // `None` if the result doesn't fit
+    fn apply(&self, counts: &[u64]) -> Option<u128> {
+        let mut counts = counts.iter().map(|c| *c as u128);
+        match self {
+            Aggregation::Product => counts.try_fold(1u128, |acc, c| acc.checked_mul(c)),
+            Aggregation::Sum => counts.try_fold(0u128, |acc, c| acc.checked_add(c)),
+        }
+    }
+}
+
+// How worry levels are kept in check after each inspection. Written as steps joined by `then`,
+// each one of `none`, `div N`, `mod N`, `mod lcm` (the least common multiple of every divisor in
+// the input) or an expression of `old`, e.g. `div 3 then old + 1 then mod lcm`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Relief {
+    None,
+    Div(Worry),
+    Mod(Worry),
+    ModLcm,
+    Custom(Operation),
+    // Applies the first one, then the second
+    Then(Box<Relief>, Box<Relief>),
+}
+
+impl Relief {
+    fn parse_step(step: &str) -> Result<Self, ScenarioError> {
+        let invalid = || ScenarioError::Relief(step.to_string());
+        let number = |n: &str| {
+            n.parse::<Worry>()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or_else(invalid)
+        };
+        match step.split_once(' ') {
+            _ if step == "none" => Ok(Relief::None),
+            Some(("div", n)) => Ok(Relief::Div(number(n)?)),
+            Some(("mod", "lcm")) => Ok(Relief::ModLcm),
+            Some(("mod", n)) => Ok(Relief::Mod(number(n)?)),
+            _ => all_consuming(Operation::parse_expression)(step)
+                .map(|(_, operation)| Relief::Custom(operation))
+                .map_err(|_| invalid()),
+        }
+    }
+
+    // `lcm` is `None` when the divisors' least common multiple doesn't fit in a `Worry`
+    fn transformer(&self, lcm: Option<Worry>) -> Result<WorryTransformer, ScenarioError> {
+        Ok(match self {
+            Relief::None => WorryTransformer::None,
+            Relief::Div(n) => WorryTransformer::FloorDiv(*n),
+            Relief::Mod(n) => WorryTransformer::Rem(*n),
+            Relief::ModLcm => WorryTransformer::Rem(lcm.ok_or(ScenarioError::NoCommonMultiple)?),
+            Relief::Custom(operation) => WorryTransformer::Custom(operation.clone()),
+            Relief::Then(first, second) => WorryTransformer::Then(
+                Box::new(first.transformer(lcm)?),
+                Box::new(second.transformer(lcm)?),
+            ),
+        })
+    }
+}
+
+impl std::str::FromStr for Relief {
+    type Err = ScenarioError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut steps = s.split(" then ").map(|step| Self::parse_step(step.trim()));
+        let first = steps.next().unwrap_or(Ok(Relief::None))?;
+        steps.try_fold(first, |acc, next| {
+            Ok(Relief::Then(Box::new(acc), Box::new(next?)))
+        })
+    }
+}
+
+impl TryFrom<String> for Relief {
+    type Error = ScenarioError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+// Everything the two parts disagree on
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    pub rounds: u64,
+    #[serde(default = "Scenario::default_relief")]
+    pub relief: Relief,
+    #[serde(default = "Scenario::default_top")]
+    pub top: usize,
+    #[serde(default = "Scenario::default_aggregation")]
+    pub aggregation: Aggregation,
+}
+
+#[derive(Debug)]
+pub enum ScenarioError {
+    Input(ParseError),
+    Relief(String),
+    NoCommonMultiple,
+    Toml(toml::de::Error),
+    Worry(WorryError),
+    TooFewMonkeys(usize),
+    Overflow,
+}
+
+impl Display for ScenarioError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScenarioError::Input(e) => write!(f, "invalid input: {}", e),
+            ScenarioError::Relief(step) => write!(f, "invalid relief step {:?}", step),
+            ScenarioError::NoCommonMultiple => {
+                write!(f, "the divisors' least common multiple is too large")
+            }
+            ScenarioError::Toml(e) => write!(f, "invalid scenario: {}", e),
+            ScenarioError::Worry(e) => write!(f, "{}", e),
+            ScenarioError::TooFewMonkeys(n) => write!(f, "only {} monkeys to pick from", n),
+            ScenarioError::Overflow => write!(f, "monkey business overflowed"),
+        }
+    }
+}
+
+impl std::error::Error for ScenarioError {}
+
+fn gcd(a: Worry, b: Worry) -> Worry {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// `None` if it doesn't fit
+fn lcm(numbers: &[Worry]) -> Option<Worry> {
+    numbers
+        .iter()
+        .try_fold(1 as Worry, |acc, n| (acc / gcd(acc, *n)).checked_mul(*n))
+}
+
+impl Scenario {
+    fn default_relief() -> Relief {
+        Relief::None
+    }
+
+    fn default_top() -> usize {
+        2
+    }
+
+    fn default_aggregation() -> Aggregation {
+        Aggregation::Product
+    }
+
+    pub fn part1() -> Self {
+        Self {
+            rounds: 20,
+            relief: Relief::Div(3),
+            top: 2,
+            aggregation: Aggregation::Product,
+        }
+    }
+
+    pub fn part2() -> Self {
+        Self {
+            rounds: 10000,
+            relief: Relief::ModLcm,
+            top: 2,
+            aggregation: Aggregation::Product,
+        }
+    }
+
+    pub fn from_toml(source: &str) -> Result<Self, ScenarioError> {
+        toml::from_str(source).map_err(ScenarioError::Toml)
+    }
+
+    pub fn run(&self, input: &str) -> Result<u128, ScenarioError> {
+        let monkeys = parse_monkeys(input, WorryTransformer::None).map_err(ScenarioError::Input)?;
+        let relief = self.relief.transformer(lcm(&divisors(&monkeys)))?;
+        if monkeys.len() < self.top {
+            return Err(ScenarioError::TooFewMonkeys(monkeys.len()));
+        }
+
+        let monkeys = monkeys
+            .into_iter()
+            .map(|mut m| {
+                m.worry_transformer = relief.clone();
+                m
+            })
+            .collect();
+        let mut inspected = Simulator::new(monkeys)
+            .simulate(self.rounds)
+            .map_err(ScenarioError::Worry)?;
+
+        inspected.sort_unstable_by(|a, b| b.cmp(a));
+        self.aggregation
+            .apply(&inspected[..self.top])
+            .ok_or(ScenarioError::Overflow)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::monkey::WorryTransformer;
+    use crate::operation::Operation;
+
+    use super::{lcm, Aggregation, Relief, Scenario, ScenarioError};
+
+    const INPUT: &str = include_str!("../input-example.txt");
+
+    #[test]
+    fn parts() {
+        assert_eq!(Scenario::part1().run(INPUT).unwrap(), 10605);
+        assert_eq!(Scenario::part2().run(INPUT).unwrap(), 2713310158);
+    }
+
+    #[test]
+    fn from_toml() {
+        let scenario = Scenario::from_toml(
+            "rounds = 20\nrelief = \"div 3 then mod lcm\"\ntop = 3\naggregation = \"sum\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(scenario.relief.transformer(Some(96577)).unwrap(), {
+            WorryTransformer::Then(
+                Box::new(WorryTransformer::FloorDiv(3)),
+                Box::new(WorryTransformer::Rem(96577)),
+            )
+        });
+        assert_eq!(scenario.aggregation, Aggregation::Sum);
+        // Inspections after round 20 in the puzzle: 101, 95, 7, 105
+        assert_eq!(scenario.run(INPUT).unwrap(), 101 + 95 + 105);
+
+        let defaults = Scenario::from_toml("rounds = 1").unwrap();
+        assert_eq!((defaults.relief, defaults.top), (Relief::None, 2));
+        assert!(matches!(
+            Scenario::from_toml("rounds = 1\nrelif = \"none\""),
+            Err(ScenarioError::Toml(_))
+        ));
+        // Relief is checked when the scenario is loaded, not when it runs
+        let error =
+            Scenario::from_toml("rounds = 1\nrelief = \"div 3 then sqrt old\"").unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("invalid relief step \"sqrt old\""));
+    }
+
+    #[test]
+    fn custom_relief() {
+        assert!(matches!(
+            "old - old / 3".parse::<Relief>(),
+            Err(ScenarioError::Relief(step)) if step == "old - old / 3"
+        ));
+
+        // Adding nothing and then taking a third is the same as part 1
+        let scenario = Scenario {
+            relief: "(old + 0) * 1 then div 3".parse().unwrap(),
+            ..Scenario::part1()
+        };
+        assert_eq!(
+            scenario.relief.transformer(None).unwrap(),
+            WorryTransformer::Then(
+                Box::new(WorryTransformer::Custom(
+                    Operation::parse_expression("(old + 0) * 1").unwrap().1
+                )),
+                Box::new(WorryTransformer::FloorDiv(3))
+            )
+        );
+        assert_eq!(scenario.run(INPUT).unwrap(), 10605);
+    }
+
+    #[test]
+    fn subtraction_under_common_multiple() {
+        // Monkey 0's items are always big enough to take 5 from, but not once they're reduced
+        let input = "Monkey 0:
+  Starting items: 6
+  Operation: new = old * 10 - 5
+  Test: divisible by 3
+    If true: throw to monkey 1
+    If false: throw to monkey 1
+
+Monkey 1:
+  Starting items: 1
+  Operation: new = old * 6
+  Test: divisible by 2
+    If true: throw to monkey 0
+    If false: throw to monkey 0
+";
+        assert_eq!(Scenario::part1().run(input).unwrap(), 1560);
+
+        // The same as playing 50 rounds with unbounded worry levels
+        let scenario = Scenario {
+            rounds: 50,
+            ..Scenario::part2()
+        };
+        assert_eq!(scenario.run(input).unwrap(), 9900);
+        assert_eq!(Scenario::part2().run(input).unwrap(), 399980000);
+    }
+
+    #[test]
+    fn errors() {
+        let top = Scenario {
+            top: 5,
+            ..Scenario::part1()
+        };
+        assert!(matches!(
+            top.run(INPUT),
+            Err(ScenarioError::TooFewMonkeys(4))
+        ));
+
+        let unreduced = Scenario {
+            relief: Relief::None,
+            ..Scenario::part2()
+        };
+        assert!(matches!(unreduced.run(INPUT), Err(ScenarioError::Worry(_))));
+
+        for relief in ["div zero", "div 0", "mod 0", "sqrt old"] {
+            assert!(matches!(
+                relief.parse::<Relief>(),
+                Err(ScenarioError::Relief(_))
+            ));
+        }
+
+        let huge = INPUT
+            .replace("divisible by 23", "divisible by 4294967291")
+            .replace("divisible by 19", "divisible by 4294967279");
+        assert!(matches!(
+            Scenario::part2().run(&huge),
+            Err(ScenarioError::NoCommonMultiple)
+        ));
+    }
+
+    #[test]
+    fn least_common_multiple() {
+        assert_eq!(lcm(&[13, 17, 19, 23]), Some(96577));
+        assert_eq!(lcm(&[4, 6, 10]), Some(60));
+        assert_eq!(lcm(&[]), Some(1));
+        assert_eq!(lcm(&[u64::MAX, 2]), None);
+
+        // With divisors that share factors, `mod lcm` is less than their product and still gives
+        // the same answer
+        let input = INPUT.replace("divisible by 13", "divisible by 46");
+        assert_eq!(lcm(&[19, 17, 46, 23]), Some(19 * 17 * 46));
+        assert_eq!(
+            Scenario::part2().run(&input).unwrap(),
+            Scenario {
+                relief: Relief::Mod(23 * 19 * 46 * 17),
+                ..Scenario::part2()
+            }
+            .run(&input)
+            .unwrap()
+        );
+    }
+}
@@ -0,0 +1,205 @@
+use crate::monkey::{Item, Monkey};
+use crate::round::round;
+use crate::worry::{WorryError, WorryNumber};
+
+// Runs the monkeys for any number of rounds without playing every one of them.
+//
+// Items never affect each other: which monkey inspects an item and what its worry becomes only
+// depend on that item. So each item is followed on its own, one round at a time, from the monkey
+// holding it at the start of the round. Once worry levels are kept in check (e.g. with
+// `WorryTransformer::Rem`) there are finitely many (monkey, worry) states, so the item eventually
+// comes back to one it was in before and from then on repeats the same inspections.
+pub struct Simulator<W> {
+    // Always empty-handed between rounds, items are handed over one at a time
+    monkeys: Vec<Monkey<W>>,
+    // The starting items, with the monkey holding each
+    items: Vec<(usize, Item<W>)>,
+}
+
+// Where an item is at the start of a round
+type State<W> = (usize, W);
+
+impl<W: WorryNumber + Eq> Simulator<W> {
+    pub fn new(mut monkeys: Vec<Monkey<W>>) -> Self {
+        let items = monkeys
+            .iter_mut()
+            .enumerate()
+            .flat_map(|(id, monkey)| {
+                std::mem::take(&mut monkey.inventory)
+                    .into_iter()
+                    .map(move |item| (id, item))
+            })
+            .collect();
+        Self { monkeys, items }
+    }
+
+    // Plays one round with only this item, adding its inspections to `inspected`
+    fn step(&mut self, state: State<W>, inspected: &mut [u64]) -> Result<State<W>, WorryError> {
+        let (monkey, worry) = state;
+        self.monkeys[monkey].inventory.push(Item { id: 0, worry });
+
+        // Round numbers only label the events
+        let events = round(&mut self.monkeys, 0)?;
+        for event in &events {
+            inspected[event.monkey] += 1;
+        }
+
+        // Every monkey plays its turn, so the item ends up with whoever threw to last
+        let holder = events.last().map_or(monkey, |e| e.target);
+        let item = self.monkeys[holder]
+            .inventory
+            .pop()
+            .expect("the item is with the last monkey it was thrown to");
+        Ok((holder, item.worry))
+    }
+
+    // Plays `rounds` rounds from `state`, adding the inspections to `inspected`
+    fn walk(
+        &mut self,
+        mut state: State<W>,
+        rounds: u64,
+        inspected: &mut [u64],
+    ) -> Result<State<W>, WorryError> {
+        for _ in 0..rounds {
+            state = self.step(state, inspected)?;
+        }
+        Ok(state)
+    }
+
+    // How many times each monkey inspects this item over `rounds` rounds.
+    //
+    // Brent's algorithm finds the cycle without remembering the states on the way, so this takes
+    // the same memory however many rounds it plays until it gets there.
+    fn follow(&mut self, start: State<W>, rounds: u64) -> Result<Vec<u64>, WorryError> {
+        let mut inspected = vec![0; self.monkeys.len()];
+        if rounds == 0 {
+            return Ok(inspected);
+        }
+
+        // The hare plays every round until it meets the tortoise, which waits at powers of two
+        let (mut power, mut length, mut played) = (1, 1, 1);
+        let mut tortoise = start.clone();
+        let mut hare = self.step(start.clone(), &mut inspected)?;
+        while hare != tortoise {
+            if played == rounds {
+                return Ok(inspected);
+            }
+            if power == length {
+                tortoise = hare.clone();
+                power *= 2;
+                length = 0;
+            }
+            hare = self.step(hare, &mut inspected)?;
+            length += 1;
+            played += 1;
+        }
+
+        // The first repeat is where the tortoise and a hare `length` rounds ahead meet
+        let mut scratch = vec![0; self.monkeys.len()];
+        let mut tortoise = start.clone();
+        let mut hare = self.walk(start.clone(), length, &mut scratch)?;
+        let mut lead_in = 0;
+        while hare != tortoise {
+            tortoise = self.step(tortoise, &mut scratch)?;
+            hare = self.step(hare, &mut scratch)?;
+            lead_in += 1;
+        }
+
+        // Rounds lead_in..lead_in + length repeat forever
+        let mut total = vec![0; self.monkeys.len()];
+        let cycle_start = self.walk(start, lead_in, &mut total)?;
+        let mut cycle = vec![0; self.monkeys.len()];
+        self.walk(cycle_start.clone(), length, &mut cycle)?;
+        let remaining = rounds - lead_in;
+        self.walk(cycle_start, remaining % length, &mut total)?;
+        for (t, c) in total.iter_mut().zip(cycle) {
+            *t += remaining / length * c;
+        }
+        Ok(total)
+    }
+
+    // How many items each monkey inspects over `rounds` rounds
+    pub fn simulate(&mut self, rounds: u64) -> Result<Vec<u64>, WorryError> {
+        let mut inspected = vec![0; self.monkeys.len()];
+        for (monkey, item) in self.items.clone() {
+            let counts = self.follow((monkey, item.worry), rounds)?;
+            inspected.iter_mut().zip(counts).for_each(|(i, c)| *i += c);
+        }
+        Ok(inspected)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::monkey::{divisors, parse_monkeys, WorryTransformer};
+    use crate::round::round;
+
+    use super::Simulator;
+
+    const INPUT: &str = include_str!("../input-example.txt");
+
+    fn simulator(input: &str, transformer: WorryTransformer) -> Simulator<u64> {
+        Simulator::new(parse_monkeys(input, transformer).unwrap())
+    }
+
+    fn reduced(input: &str) -> Simulator<u64> {
+        let monkeys = parse_monkeys(input, WorryTransformer::None).unwrap();
+        let common_multiple = divisors(&monkeys).iter().product();
+        simulator(input, WorryTransformer::Rem(common_multiple))
+    }
+
+    // Plays every round like the puzzle describes
+    fn brute_force(input: &str, transformer: WorryTransformer, rounds: u64) -> Vec<u64> {
+        let mut monkeys = parse_monkeys(input, transformer).unwrap();
+        for r in 1..=rounds as usize {
+            round(&mut monkeys, r).unwrap();
+        }
+        monkeys.iter().map(|m| m.inspected as u64).collect()
+    }
+
+    #[test]
+    fn example() {
+        let mut simulator = reduced(INPUT);
+
+        assert_eq!(simulator.simulate(1), Ok(vec![2, 4, 3, 6]));
+        assert_eq!(
+            simulator.simulate(10000),
+            Ok(vec![52166, 47830, 1938, 52013])
+        );
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        for input in [INPUT, include_str!("../input.txt")] {
+            let common_multiple = divisors(&parse_monkeys(input, WorryTransformer::None).unwrap())
+                .iter()
+                .product();
+            let transformer = WorryTransformer::Rem(common_multiple);
+            let mut simulator = reduced(input);
+            for rounds in (0..50).chain([137, 1000, 2500]) {
+                assert_eq!(
+                    simulator.simulate(rounds),
+                    Ok(brute_force(input, transformer.clone(), rounds)),
+                    "{} rounds",
+                    rounds
+                );
+            }
+        }
+
+        let mut relieved = simulator(INPUT, WorryTransformer::FloorDiv(3));
+        assert_eq!(
+            relieved.simulate(20),
+            Ok(brute_force(INPUT, WorryTransformer::FloorDiv(3), 20))
+        );
+    }
+
+    #[test]
+    fn many_rounds() {
+        let mut simulator = reduced(include_str!("../input.txt"));
+
+        let inspected = simulator.simulate(1_000_000_000_000).unwrap();
+
+        assert_eq!(inspected.len(), 8);
+        assert!(inspected.iter().all(|i| *i > 100_000_000_000));
+    }
+}
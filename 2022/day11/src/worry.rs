@@ -0,0 +1,159 @@
+use std::fmt::{Debug, Display};
+
+use num_bigint::BigUint;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorryError {
+    Overflow,
+    Underflow,
+    // The representation can't answer this, e.g. dividing a number it only knows residues of
+    Unsupported,
+}
+
+impl Display for WorryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WorryError::Overflow => write!(f, "worry level overflowed"),
+            WorryError::Underflow => write!(f, "worry level went below zero"),
+            WorryError::Unsupported => write!(f, "operation not supported by this worry type"),
+        }
+    }
+}
+
+impl std::error::Error for WorryError {}
+
+// Everything the simulation needs from a worry level. The puzzle's constants (operands, divisors,
+// relief) always fit in a `u64`, so they're passed as such and `lift`ed when needed.
+pub trait WorryNumber: Clone + Debug + Display {
+    // Turns a constant into the same representation as `self`
+    fn lift(&self, n: u64) -> Self;
+
+    fn add(&self, other: &Self) -> Result<Self, WorryError>;
+    fn sub(&self, other: &Self) -> Result<Self, WorryError>;
+    fn mul(&self, other: &Self) -> Result<Self, WorryError>;
+
+    fn div(&self, n: u64) -> Result<Self, WorryError>;
+    fn rem(&self, n: u64) -> Result<Self, WorryError>;
+    fn divisible_by(&self, n: u64) -> Result<bool, WorryError>;
+}
+
+impl WorryNumber for u64 {
+    fn lift(&self, n: u64) -> Self {
+        n
+    }
+
+    fn add(&self, other: &Self) -> Result<Self, WorryError> {
+        self.checked_add(*other).ok_or(WorryError::Overflow)
+    }
+
+    fn sub(&self, other: &Self) -> Result<Self, WorryError> {
+        self.checked_sub(*other).ok_or(WorryError::Underflow)
+    }
+
+    fn mul(&self, other: &Self) -> Result<Self, WorryError> {
+        self.checked_mul(*other).ok_or(WorryError::Overflow)
+    }
+
+    fn div(&self, n: u64) -> Result<Self, WorryError> {
+        Ok(self / n)
+    }
+
+    fn rem(&self, n: u64) -> Result<Self, WorryError> {
+        Ok(self % n)
+    }
+
+    fn divisible_by(&self, n: u64) -> Result<bool, WorryError> {
+        Ok(self.is_multiple_of(n))
+    }
+}
+
+impl WorryNumber for u128 {
+    fn lift(&self, n: u64) -> Self {
+        n as u128
+    }
+
+    fn add(&self, other: &Self) -> Result<Self, WorryError> {
+        self.checked_add(*other).ok_or(WorryError::Overflow)
+    }
+
+    fn sub(&self, other: &Self) -> Result<Self, WorryError> {
+        self.checked_sub(*other).ok_or(WorryError::Underflow)
+    }
+
+    fn mul(&self, other: &Self) -> Result<Self, WorryError> {
+        self.checked_mul(*other).ok_or(WorryError::Overflow)
+    }
+
+    fn div(&self, n: u64) -> Result<Self, WorryError> {
+        Ok(self / n as u128)
+    }
+
+    fn rem(&self, n: u64) -> Result<Self, WorryError> {
+        Ok(self % n as u128)
+    }
+
+    fn divisible_by(&self, n: u64) -> Result<bool, WorryError> {
+        Ok(self.is_multiple_of(n as u128))
+    }
+}
+
+impl WorryNumber for BigUint {
+    fn lift(&self, n: u64) -> Self {
+        n.into()
+    }
+
+    fn add(&self, other: &Self) -> Result<Self, WorryError> {
+        Ok(self + other)
+    }
+
+    fn sub(&self, other: &Self) -> Result<Self, WorryError> {
+        if self < other {
+            return Err(WorryError::Underflow);
+        }
+        Ok(self - other)
+    }
+
+    fn mul(&self, other: &Self) -> Result<Self, WorryError> {
+        Ok(self * other)
+    }
+
+    fn div(&self, n: u64) -> Result<Self, WorryError> {
+        Ok(self / n)
+    }
+
+    fn rem(&self, n: u64) -> Result<Self, WorryError> {
+        Ok(self % n)
+    }
+
+    fn divisible_by(&self, n: u64) -> Result<bool, WorryError> {
+        Ok(self % n == BigUint::default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use num_bigint::BigUint;
+
+    use super::{WorryError, WorryNumber};
+
+    #[test]
+    fn u64_is_checked() {
+        assert_eq!(u64::MAX.mul(&2), Err(WorryError::Overflow));
+        assert_eq!(u64::MAX.add(&1), Err(WorryError::Overflow));
+        assert_eq!(1u64.sub(&2), Err(WorryError::Underflow));
+        assert_eq!(7u64.mul(&6), Ok(42));
+    }
+
+    #[test]
+    fn wider_types() {
+        let big = (u64::MAX as u128).mul(&(u64::MAX as u128)).unwrap();
+        assert_eq!(big.divisible_by(u64::MAX), Ok(true));
+        assert_eq!(big.mul(&big), Err(WorryError::Overflow));
+
+        let n = BigUint::from(u64::MAX);
+        let square = n.mul(&n).unwrap().mul(&n).unwrap();
+        assert_eq!(square.divisible_by(u64::MAX), Ok(true));
+        assert_eq!(square.div(u64::MAX).unwrap().div(u64::MAX), Ok(n.clone()));
+        assert_eq!(n.sub(&square), Err(WorryError::Underflow));
+    }
+}
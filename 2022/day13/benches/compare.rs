@@ -0,0 +1,88 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use day13::flat::Arena;
+use day13::testing::random_packet;
+use day13::{ordered_pairs, parse_packets, parse_pairs, part2};
+
+// Pairs of packets that share a long prefix, so comparisons have to go deep before they're decided
+fn generate(pairs: usize, mut seed: u64) -> String {
+    (0..pairs)
+        .map(|_| {
+            let shared = random_packet(&mut seed, 1);
+            let (a, b) = (random_packet(&mut seed, 1), random_packet(&mut seed, 1));
+            format!("[{},{}]\n[{},{}]\n", shared, a, shared, b)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    for pairs in [1_000, 10_000] {
+        let input = generate(pairs, 0xbe4c);
+        group.bench_with_input(BenchmarkId::new("tree", pairs), &input, |b, input| {
+            b.iter(|| parse_packets(black_box(input)).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("flat", pairs), &input, |b, input| {
+            b.iter(|| Arena::parse(black_box(input)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn compare(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ordered pairs");
+    for pairs in [1_000, 10_000] {
+        let input = generate(pairs, 0xbe4c);
+        let tree = parse_pairs(&input).unwrap();
+        let arena = Arena::parse(&input).unwrap();
+        group.bench_with_input(BenchmarkId::new("tree", pairs), &tree, |b, tree| {
+            b.iter(|| ordered_pairs(black_box(tree)))
+        });
+        group.bench_with_input(BenchmarkId::new("flat", pairs), &arena, |b, arena| {
+            b.iter(|| day13::flat::ordered_pairs(black_box(arena)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn sort(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sort");
+    for pairs in [1_000, 10_000] {
+        let input = generate(pairs, 0xbe4c);
+        let tree = parse_packets(&input).unwrap();
+        let arena = Arena::parse(&input).unwrap();
+        group.bench_with_input(BenchmarkId::new("tree", pairs), &tree, |b, tree| {
+            b.iter(|| {
+                let mut tree = tree.clone();
+                tree.sort();
+                tree
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("flat", pairs), &arena, |b, arena| {
+            b.iter(|| {
+                let mut packets = arena.iter().collect::<Vec<_>>();
+                packets.sort();
+                packets.len()
+            })
+        });
+    }
+    group.finish();
+}
+
+// Packets are parsed as part of it, since not keeping them around is the point
+fn decoder_key_from_input(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decoder key");
+    for pairs in [1_000, 10_000] {
+        let input = generate(pairs, 0xbe4c);
+        group.bench_with_input(BenchmarkId::new("tree", pairs), &input, |b, input| {
+            b.iter(|| part2(black_box(input)).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("flat", pairs), &input, |b, input| {
+            b.iter(|| day13::flat::decoder_key(&Arena::parse(black_box(input)).unwrap()))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, parse, compare, sort, decoder_key_from_input);
+criterion_main!(benches);
@@ -0,0 +1,15 @@
+#![no_main]
+
+use day13::SignalElement;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    if let Ok((_, packet)) = SignalElement::parse(input) {
+        // Whatever parses prints back to something that parses to the same packet
+        let printed = packet.to_string();
+        let (rest, reparsed) = SignalElement::parse(&printed).expect("printed packet parses");
+        assert_eq!(rest, "");
+        assert_eq!(format!("{:?}", reparsed), format!("{:?}", packet));
+        assert_eq!(reparsed.cmp(&packet), std::cmp::Ordering::Equal);
+    }
+});
@@ -0,0 +1,279 @@
+use std::cmp::Ordering;
+use std::fmt::Display;
+
+use crate::SignalElement;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl Display for Side {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Side::Left => f.write_str("left"),
+            Side::Right => f.write_str("right"),
+        }
+    }
+}
+
+// One line of the walkthrough. `depth` is how far it's indented.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    Compare {
+        depth: usize,
+        left: SignalElement,
+        right: SignalElement,
+    },
+    // `side` was an integer or a string next to a list, and got wrapped in a list
+    Promote {
+        depth: usize,
+        side: Side,
+        promoted: SignalElement,
+    },
+    // The comparison ended because `side` had the smaller value
+    Smaller {
+        depth: usize,
+        side: Side,
+    },
+    // The comparison ended because `side` had no more items
+    RanOut {
+        depth: usize,
+        side: Side,
+    },
+}
+
+// Every comparison `Ord` makes to order two packets, and what it decided
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explanation {
+    pub steps: Vec<Step>,
+    pub ordering: Ordering,
+}
+
+// Describes how `left.cmp(right)` gets to its answer, step by step
+pub fn explain(left: &SignalElement, right: &SignalElement) -> Explanation {
+    let mut steps = vec![];
+    let ordering = compare(left, right, 0, &mut steps);
+    Explanation { steps, ordering }
+}
+
+// Which side the smaller one was, if they weren't equal
+fn smaller(ordering: Ordering) -> Option<Side> {
+    match ordering {
+        Ordering::Less => Some(Side::Left),
+        Ordering::Equal => None,
+        Ordering::Greater => Some(Side::Right),
+    }
+}
+
+// Follows `Ord for SignalElement` arm by arm, recording each step on the way
+fn compare(
+    left: &SignalElement,
+    right: &SignalElement,
+    depth: usize,
+    steps: &mut Vec<Step>,
+) -> Ordering {
+    steps.push(Step::Compare {
+        depth,
+        left: left.clone(),
+        right: right.clone(),
+    });
+
+    match (left, right) {
+        (SignalElement::Multiple(l), SignalElement::Multiple(r)) => {
+            for (a, b) in l.iter().zip(r) {
+                let ordering = compare(a, b, depth + 1, steps);
+                if ordering.is_ne() {
+                    return ordering;
+                }
+            }
+            let ordering = l.len().cmp(&r.len());
+            if let Some(side) = smaller(ordering) {
+                steps.push(Step::RanOut {
+                    depth: depth + 1,
+                    side,
+                });
+            }
+            ordering
+        }
+        (SignalElement::Multiple(_), other) => {
+            let promoted = SignalElement::Multiple(vec![other.clone()]);
+            steps.push(Step::Promote {
+                depth: depth + 1,
+                side: Side::Right,
+                promoted: promoted.clone(),
+            });
+            compare(left, &promoted, depth + 1, steps)
+        }
+        (other, SignalElement::Multiple(_)) => {
+            let promoted = SignalElement::Multiple(vec![other.clone()]);
+            steps.push(Step::Promote {
+                depth: depth + 1,
+                side: Side::Left,
+                promoted: promoted.clone(),
+            });
+            compare(&promoted, right, depth + 1, steps)
+        }
+        _ => {
+            let ordering = left.cmp(right);
+            if let Some(side) = smaller(ordering) {
+                steps.push(Step::Smaller {
+                    depth: depth + 1,
+                    side,
+                });
+            }
+            ordering
+        }
+    }
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Sentences start with the side, and say what it means for the pair
+        let verdict = |side: &Side| match side {
+            Side::Left => ("Left", "so inputs are in the right order"),
+            Side::Right => ("Right", "so inputs are not in the right order"),
+        };
+        match self {
+            Step::Compare { depth, left, right } => {
+                write!(f, "{:1$}- Compare {2} vs {3}", "", depth * 2, left, right)
+            }
+            Step::Promote {
+                depth,
+                side,
+                promoted,
+            } => write!(
+                f,
+                "{:1$}- Mixed types; convert {2} to {3} and retry comparison",
+                "",
+                depth * 2,
+                side,
+                promoted
+            ),
+            Step::Smaller { depth, side } => {
+                let (side, verdict) = verdict(side);
+                write!(
+                    f,
+                    "{:1$}- {2} side is smaller, {3}",
+                    "",
+                    depth * 2,
+                    side,
+                    verdict
+                )
+            }
+            Step::RanOut { depth, side } => {
+                let (side, verdict) = verdict(side);
+                write!(
+                    f,
+                    "{:1$}- {2} side ran out of items, {3}",
+                    "",
+                    depth * 2,
+                    side,
+                    verdict
+                )
+            }
+        }
+    }
+}
+
+// Rendered like the puzzle's walkthrough, one step per line
+impl Display for Explanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for step in &self.steps {
+            writeln!(f, "{}", step)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::testing::random_packet;
+    use crate::SignalElement;
+
+    use super::explain;
+
+    fn packet(s: &str) -> SignalElement {
+        SignalElement::parse(s).unwrap().1
+    }
+
+    #[test]
+    fn walkthrough() {
+        // Pairs 2, 3 and 5 of the puzzle's example, word for word
+        assert_eq!(
+            explain(&packet("[[1],[2,3,4]]"), &packet("[[1],4]")).to_string(),
+            "- Compare [[1],[2,3,4]] vs [[1],4]
+  - Compare [1] vs [1]
+    - Compare 1 vs 1
+  - Compare [2,3,4] vs 4
+    - Mixed types; convert right to [4] and retry comparison
+    - Compare [2,3,4] vs [4]
+      - Compare 2 vs 4
+        - Left side is smaller, so inputs are in the right order
+"
+        );
+        assert_eq!(
+            explain(&packet("[9]"), &packet("[[8,7,6]]")).to_string(),
+            "- Compare [9] vs [[8,7,6]]
+  - Compare 9 vs [8,7,6]
+    - Mixed types; convert left to [9] and retry comparison
+    - Compare [9] vs [8,7,6]
+      - Compare 9 vs 8
+        - Right side is smaller, so inputs are not in the right order
+"
+        );
+        assert_eq!(
+            explain(&packet("[7,7,7,7]"), &packet("[7,7,7]")).to_string(),
+            "- Compare [7,7,7,7] vs [7,7,7]
+  - Compare 7 vs 7
+  - Compare 7 vs 7
+  - Compare 7 vs 7
+  - Right side ran out of items, so inputs are not in the right order
+"
+        );
+    }
+
+    #[test]
+    fn strings() {
+        let explanation = explain(&packet("[1,\"a\"]"), &packet("[1,[\"b\"]]"));
+
+        assert_eq!(
+            explanation.to_string(),
+            "- Compare [1,\"a\"] vs [1,[\"b\"]]
+  - Compare 1 vs 1
+  - Compare \"a\" vs [\"b\"]
+    - Mixed types; convert left to [\"a\"] and retry comparison
+    - Compare [\"a\"] vs [\"b\"]
+      - Compare \"a\" vs \"b\"
+        - Left side is smaller, so inputs are in the right order
+"
+        );
+    }
+
+    #[test]
+    fn agrees_with_ord() {
+        let mut seed = 0xe7a1;
+        for _ in 0..500 {
+            let (left, right) = (random_packet(&mut seed, 0), random_packet(&mut seed, 0));
+
+            let explanation = explain(&left, &right);
+
+            assert_eq!(
+                explanation.ordering,
+                left.cmp(&right),
+                "{} vs {}",
+                left,
+                right
+            );
+            // Equal packets get no verdict
+            assert_eq!(
+                explanation.ordering.is_eq(),
+                explanation
+                    .to_string()
+                    .lines()
+                    .all(|l| l.contains("Compare") || l.contains("Mixed types"))
+            );
+        }
+    }
+}
@@ -0,0 +1,376 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::ops::Range;
+
+use nom::character::complete::{char, multispace0};
+use nom::error::{Error, ErrorKind};
+use nom::IResult;
+
+use crate::{integer, json_string, ParseError};
+
+// A packet flattened to the order its brackets and values are written in, so `[1,[2]]` is
+// `Open, Integer(1), Open, Integer(2), Close, Close`. Strings borrow from the input unless they had
+// escapes to decode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token<'a> {
+    Open,
+    Close,
+    Integer(i64),
+    Text(Cow<'a, str>),
+}
+
+// Every packet of an input in one buffer of tokens, instead of a `Vec` for each list
+#[derive(Debug, Default)]
+pub struct Arena<'a> {
+    tokens: Vec<Token<'a>>,
+    packets: Vec<Range<usize>>,
+    // The packets of each block of lines between blank lines, with the line the block starts on
+    blocks: Vec<(usize, Range<usize>)>,
+}
+
+// One packet's tokens, ordered the same way as `SignalElement`
+#[derive(Debug, Clone, Copy)]
+pub struct Packet<'p, 'a> {
+    tokens: &'p [Token<'a>],
+}
+
+// A value, with whitespace around it. Nothing here recurses, so there's no limit on nesting.
+fn value<'a>(input: &'a str, tokens: &mut Vec<Token<'a>>) -> IResult<&'a str, ()> {
+    let mut depth = 0usize;
+    let mut input = multispace0(input)?.0;
+    loop {
+        // Where a value goes: an integer, a string or a list
+        if let Ok((rest, _)) = char::<_, Error<&str>>('[')(input) {
+            tokens.push(Token::Open);
+            depth += 1;
+            input = multispace0(rest)?.0;
+            if let Ok((rest, _)) = char::<_, Error<&str>>(']')(input) {
+                tokens.push(Token::Close);
+                depth -= 1;
+                input = rest;
+            } else {
+                continue;
+            }
+        } else if let Ok((rest, n)) = integer(input) {
+            tokens.push(Token::Integer(n));
+            input = rest;
+        } else {
+            let (rest, raw) = json_string(input)?;
+            let inner = &raw[1..raw.len() - 1];
+            let text = if inner.contains('\\') {
+                let decoded = serde_json::from_str(raw)
+                    .map_err(|_| nom::Err::Error(Error::new(input, ErrorKind::MapRes)))?;
+                Cow::Owned(decoded)
+            } else {
+                Cow::Borrowed(inner)
+            };
+            tokens.push(Token::Text(text));
+            input = rest;
+        }
+
+        // After a value: the list it's in goes on or ends, unless there's no list
+        loop {
+            input = multispace0(input)?.0;
+            if depth == 0 {
+                return Ok((input, ()));
+            }
+            if let Ok((rest, _)) = char::<_, Error<&str>>(',')(input) {
+                input = multispace0(rest)?.0;
+                break;
+            }
+            input = char(']')(input)?.0;
+            tokens.push(Token::Close);
+            depth -= 1;
+        }
+    }
+}
+
+impl<'a> Arena<'a> {
+    // One packet per non-empty line, like `packets`, keeping track of the blocks `parse_pairs`
+    // pairs them up by
+    pub fn parse(input: &'a str) -> Result<Self, ParseError> {
+        let mut arena = Self::default();
+        let mut block = None;
+        // A blank line at the end closes the last block
+        for (i, line) in input.lines().chain([""]).enumerate() {
+            if !line.trim().is_empty() {
+                arena
+                    .push(line)
+                    .map_err(|_| ParseError::Invalid { line: i + 1 })?;
+                block.get_or_insert((i + 1, arena.len() - 1));
+            } else if let Some((line, first)) = block.take() {
+                arena.blocks.push((line, first..arena.len()));
+            }
+        }
+        Ok(arena)
+    }
+
+    // Adds the packet `input` is made of
+    fn push(&mut self, input: &'a str) -> Result<(), nom::Err<Error<&'a str>>> {
+        let start = self.tokens.len();
+        match value(input, &mut self.tokens) {
+            Ok(("", ())) => {
+                self.packets.push(start..self.tokens.len());
+                Ok(())
+            }
+            result => {
+                self.tokens.truncate(start);
+                let rest = result.map(|(rest, _)| rest)?;
+                Err(nom::Err::Error(Error::new(rest, ErrorKind::Eof)))
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.packets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packets.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<Packet<'_, 'a>> {
+        self.packets.get(index).map(|range| Packet {
+            tokens: &self.tokens[range.clone()],
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Packet<'_, 'a>> + '_ {
+        (0..self.len()).filter_map(|i| self.get(i))
+    }
+}
+
+impl<'p, 'a> Packet<'p, 'a> {
+    pub fn tokens(&self) -> &'p [Token<'a>] {
+        self.tokens
+    }
+}
+
+// Reads one packet's tokens, with lists made up on the spot for integers and strings that are
+// compared to a list
+struct Cursor<'p, 'a> {
+    tokens: &'p [Token<'a>],
+    // Lists the next value is wrapped in that aren't in `tokens`
+    wraps: usize,
+    // Closing brackets of those lists still to come after the value
+    closes: usize,
+}
+
+impl<'p, 'a> Cursor<'p, 'a> {
+    fn peek(&self) -> Option<&'p Token<'a>> {
+        if self.closes > 0 {
+            Some(&Token::Close)
+        } else {
+            self.tokens.first()
+        }
+    }
+
+    fn advance(&mut self) {
+        if self.closes > 0 {
+            self.closes -= 1;
+        } else {
+            self.tokens = &self.tokens[1..];
+        }
+    }
+
+    // Moves past an integer or a string, and onto the lists it was wrapped in
+    fn advance_value(&mut self) {
+        self.advance();
+        self.closes = std::mem::take(&mut self.wraps);
+    }
+}
+
+impl Ord for Packet<'_, '_> {
+    // Walks both packets side by side, the same comparisons `SignalElement::cmp` makes but
+    // without building anything
+    fn cmp(&self, other: &Self) -> Ordering {
+        let mut left = Cursor {
+            tokens: self.tokens,
+            wraps: 0,
+            closes: 0,
+        };
+        let mut right = Cursor {
+            tokens: other.tokens,
+            wraps: 0,
+            closes: 0,
+        };
+
+        loop {
+            match (left.peek(), right.peek()) {
+                (None, None) => return Ordering::Equal,
+                (Some(Token::Open), Some(Token::Open))
+                | (Some(Token::Close), Some(Token::Close)) => {
+                    left.advance();
+                    right.advance();
+                }
+                // A side whose list ran out first is smaller
+                (None | Some(Token::Close), _) => return Ordering::Less,
+                (_, None | Some(Token::Close)) => return Ordering::Greater,
+                (Some(Token::Open), Some(_)) => {
+                    left.advance();
+                    right.wraps += 1;
+                }
+                (Some(_), Some(Token::Open)) => {
+                    right.advance();
+                    left.wraps += 1;
+                }
+                (Some(a), Some(b)) => {
+                    let ordering = match (a, b) {
+                        (Token::Integer(a), Token::Integer(b)) => a.cmp(b),
+                        (Token::Text(a), Token::Text(b)) => a.cmp(b),
+                        (Token::Integer(_), _) => Ordering::Less,
+                        _ => Ordering::Greater,
+                    };
+                    if ordering.is_ne() {
+                        return ordering;
+                    }
+                    left.advance_value();
+                    right.advance_value();
+                }
+            }
+        }
+    }
+}
+
+impl PartialOrd for Packet<'_, '_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Packet<'_, '_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Packet<'_, '_> {}
+
+// Same as `ordered_pairs` on what `parse_pairs` gives, so every block has to be a pair
+pub fn ordered_pairs(arena: &Arena) -> Result<usize, ParseError> {
+    let mut sum = 0;
+    for (i, (line, packets)) in arena.blocks.iter().enumerate() {
+        if packets.len() != 2 {
+            return Err(ParseError::NotAPair {
+                line: *line,
+                packets: packets.len(),
+            });
+        }
+        if arena.get(packets.start) <= arena.get(packets.start + 1) {
+            sum += i + 1;
+        }
+    }
+    Ok(sum)
+}
+
+// Same as `decoder_key`, counting what sorts before each divider without moving or copying any
+// packets
+pub fn decoder_key(arena: &Arena) -> usize {
+    let dividers = Arena::parse("[[2]]\n[[6]]").expect("valid divider packets");
+    let (two, six) = (dividers.get(0).unwrap(), dividers.get(1).unwrap());
+
+    // 1-based, and [[2]] always goes before [[6]]
+    let (mut before_two, mut before_six) = (1, 2);
+    for packet in arena.iter() {
+        before_two += usize::from(packet <= two);
+        before_six += usize::from(packet <= six);
+    }
+    before_two * before_six
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+
+    use crate::testing::random_packet;
+
+    use super::{decoder_key, ordered_pairs, Arena, Token};
+
+    const INPUT: &str = include_str!("../input-example.txt");
+
+    #[test]
+    fn tokens() {
+        let arena = Arena::parse("[1,[2, []]]\n\n -3 \n[\"a\", \"\\u00e9\"]").unwrap();
+
+        assert_eq!(arena.len(), 3);
+        assert_eq!(
+            arena.get(0).unwrap().tokens(),
+            [
+                Token::Open,
+                Token::Integer(1),
+                Token::Open,
+                Token::Integer(2),
+                Token::Open,
+                Token::Close,
+                Token::Close,
+                Token::Close
+            ]
+        );
+        assert_eq!(arena.get(1).unwrap().tokens(), [Token::Integer(-3)]);
+        let text = arena.get(2).unwrap().tokens();
+        assert!(matches!(text[1], Token::Text(Cow::Borrowed("a"))));
+        assert!(matches!(&text[2], Token::Text(Cow::Owned(e)) if e == "é"));
+
+        for invalid in ["[1,]", "[1 2]", "[[1]", "[1]]", "[,]", "\"a", "1 2", "x"] {
+            assert!(Arena::parse(invalid).is_err(), "{}", invalid);
+        }
+        let deep = format!("{}{}", "[".repeat(100_000), "]".repeat(100_000));
+        assert_eq!(Arena::parse(&deep).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn parts() {
+        for input in [INPUT, include_str!("../input.txt")] {
+            let arena = Arena::parse(input).unwrap();
+            assert_eq!(ordered_pairs(&arena), crate::part1(input));
+            assert_eq!(Ok(decoder_key(&arena)), crate::part2(input));
+        }
+    }
+
+    #[test]
+    fn same_order_as_tree() {
+        let mut seed = 0xf1a7;
+        let packets = (0..200)
+            .map(|_| random_packet(&mut seed, 0))
+            .collect::<Vec<_>>();
+        let text = packets
+            .iter()
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let arena = Arena::parse(&text).unwrap();
+
+        for (i, a) in packets.iter().enumerate() {
+            for (j, b) in packets.iter().enumerate() {
+                assert_eq!(arena.get(i).cmp(&arena.get(j)), a.cmp(b), "{} vs {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn same_errors_as_tree() {
+        for input in [
+            "[1]\n[2]\n\n[3]",
+            "[1]\n\n[2]\n[3]\n[4]",
+            "[1]\n[2]\n\n\n[3]\n[4]\n[5]\n\n[6]\n[7]",
+            "[1,[2]\n[1,2]\n\n[3]\n[4]\n",
+            "[1]\n[1,2]]\n",
+            "[1]\n\n[2] x\n",
+            "[1]\n[2]\n\n\n[3]\n[3]\n\n",
+        ] {
+            let arena = Arena::parse(input);
+            assert_eq!(
+                arena.as_ref().map_err(Clone::clone).and_then(ordered_pairs),
+                crate::part1(input),
+                "{:?}",
+                input
+            );
+            assert_eq!(
+                arena.map(|arena| decoder_key(&arena)),
+                crate::part2(input),
+                "{:?}",
+                input
+            );
+        }
+    }
+}
@@ -0,0 +1,912 @@
+pub mod explain;
+pub mod flat;
+use std::borrow::Borrow;
+use std::fmt::Display;
+use std::hash::{Hash, Hasher};
+
+use nom::bytes::complete::tag;
+use nom::character::complete::{anychar, char, digit1, multispace0, none_of};
+use nom::combinator::{opt, recognize};
+use nom::error::ErrorKind;
+use nom::multi::{many0_count, separated_list0};
+use nom::sequence::{delimited, pair, preceded};
+use nom::IResult;
+use serde::{Deserialize, Serialize};
+
+// How deep `SignalElement::parse` lets lists nest; `from_json` has serde_json's recursion limit.
+// Parsing, comparing and printing packets all recurse, so deeper packets could overflow the stack.
+pub const MAX_DEPTH: usize = 128;
+
+fn integer(input: &str) -> IResult<&str, i64> {
+    nom::combinator::map_res(recognize(pair(opt(char('-')), digit1)), str::parse)(input)
+}
+
+// JSON string syntax, quotes included. The escapes are left to serde_json.
+fn json_string(input: &str) -> IResult<&str, &str> {
+    recognize(delimited(
+        char('"'),
+        many0_count(nom::branch::alt((
+            nom::combinator::map(none_of("\"\\"), |_| ()),
+            nom::combinator::map(preceded(char('\\'), anychar), |_| ()),
+        ))),
+        char('"'),
+    ))(input)
+}
+
+// A packet is any JSON value made of integers, strings and lists. Strings sort after integers and
+// among themselves by their bytes; next to a list they're promoted to a one element list, same as
+// integers.
+#[derive(Debug, Eq, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SignalElement {
+    Single(i64),
+    Text(String),
+    Multiple(Vec<SignalElement>),
+}
+
+impl SignalElement {
+    // One element per line, nested lists indented by two more spaces
+    fn fmt_pretty(&self, f: &mut std::fmt::Formatter<'_>, depth: usize) -> std::fmt::Result {
+        match self {
+            SignalElement::Multiple(v) if !v.is_empty() => {
+                f.write_str("[\n")?;
+                for (i, element) in v.iter().enumerate() {
+                    f.write_fmt(format_args!("{:1$}", "", (depth + 1) * 2))?;
+                    element.fmt_pretty(f, depth + 1)?;
+                    f.write_str(if i + 1 < v.len() { ",\n" } else { "\n" })?;
+                }
+                f.write_fmt(format_args!("{:1$}]", "", depth * 2))
+            }
+            _ => f.write_fmt(format_args!("{}", self)),
+        }
+    }
+}
+
+// The packet syntax from the puzzle, `[1,[2,3]]`. With `{:#}` it's spread over several lines.
+impl Display for SignalElement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if f.alternate() {
+            return self.fmt_pretty(f, 0);
+        }
+
+        match self {
+            SignalElement::Single(n) => f.write_fmt(format_args!("{}", n)),
+            SignalElement::Text(t) => {
+                f.write_str(&serde_json::to_string(t).map_err(|_| std::fmt::Error)?)
+            }
+            SignalElement::Multiple(v) => {
+                f.write_str("[")?;
+                for (i, element) in v.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    f.write_fmt(format_args!("{}", element))?;
+                }
+                f.write_str("]")
+            }
+        }
+    }
+}
+
+impl SignalElement {
+    // The integer or string this is a wrap of, as in `[[2]]` for `2`. Wraps compare equal to what
+    // they wrap and nothing else does, since a list with more or fewer items never ties.
+    fn unwrapped(&self) -> Option<&Self> {
+        match self {
+            SignalElement::Multiple(v) if v.len() == 1 => v[0].unwrapped(),
+            SignalElement::Multiple(_) => None,
+            _ => Some(self),
+        }
+    }
+
+    // The same packet with every wrap undone, so equal packets have the same normal form
+    pub fn normalized(&self) -> Self {
+        match (self.unwrapped(), self) {
+            (Some(inner), _) => inner.clone(),
+            (None, SignalElement::Multiple(v)) => {
+                SignalElement::Multiple(v.iter().map(Self::normalized).collect())
+            }
+            (None, other) => other.clone(),
+        }
+    }
+}
+
+// Packets are equal when neither sorts before the other
+impl PartialEq for SignalElement {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+// Hashes the normal form, without building it
+impl Hash for SignalElement {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self.unwrapped().unwrap_or(self) {
+            SignalElement::Single(n) => (0u8, n).hash(state),
+            SignalElement::Text(t) => (1u8, t).hash(state),
+            SignalElement::Multiple(v) => {
+                (2u8, v.len()).hash(state);
+                v.iter().for_each(|element| element.hash(state));
+            }
+        }
+    }
+}
+
+impl PartialOrd for SignalElement {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SignalElement {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        match (self, other) {
+            (SignalElement::Single(a), SignalElement::Single(b)) => a.cmp(b),
+            (SignalElement::Text(a), SignalElement::Text(b)) => a.cmp(b),
+            (SignalElement::Single(_), SignalElement::Text(_)) => std::cmp::Ordering::Less,
+            (SignalElement::Text(_), SignalElement::Single(_)) => std::cmp::Ordering::Greater,
+            (SignalElement::Multiple(left), SignalElement::Multiple(right)) => left
+                .iter()
+                .zip(right.iter())
+                // Compare inner elements recursively
+                .map(|(l, r)| l.cmp(r))
+                // Check if there are any unequal elements, return the Ordering of the first
+                // unequal element found.
+                .find(|cmp| cmp.is_ne())
+                // If all checked elements are the same, return the Ordering of the length of the
+                // contents, as [] < [1] in our case
+                .unwrap_or_else(|| left.len().cmp(&right.len())),
+            (m @ SignalElement::Multiple(_), s) => m.cmp(&SignalElement::Multiple(vec![s.clone()])),
+            (s, m @ SignalElement::Multiple(_)) => SignalElement::Multiple(vec![s.clone()]).cmp(m),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_ord {
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::{BTreeSet, HashSet};
+    use std::hash::{Hash, Hasher};
+
+    use crate::testing::random_packet;
+    use crate::SignalElement;
+
+    fn hash(packet: &SignalElement) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        packet.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn wrapped(mut packet: SignalElement, times: usize) -> SignalElement {
+        for _ in 0..times {
+            packet = SignalElement::Multiple(vec![packet]);
+        }
+        packet
+    }
+
+    #[test]
+    fn nested_wraps() {
+        for inner in [
+            SignalElement::Single(2),
+            SignalElement::Text("a".to_string()),
+        ] {
+            let wraps = (0..6)
+                .map(|times| wrapped(inner.clone(), times))
+                .collect::<Vec<_>>();
+            for a in &wraps {
+                for b in &wraps {
+                    assert_eq!(a, b);
+                    assert_eq!(a.cmp(b), std::cmp::Ordering::Equal);
+                    assert_eq!(hash(a), hash(b));
+                }
+                assert_eq!(format!("{:?}", a.normalized()), format!("{:?}", inner));
+            }
+            assert_eq!(wraps.iter().collect::<HashSet<_>>().len(), 1);
+            assert_eq!(wraps.iter().collect::<BTreeSet<_>>().len(), 1);
+        }
+
+        // Wraps inside lists are undone too, but lists of any other length are never wraps
+        let packet = SignalElement::parse("[[[1]],[[2,3]],[[]],[]]").unwrap().1;
+        assert_eq!(packet.normalized().to_string(), "[1,[[2,3]],[[]],[]]");
+        assert_eq!(
+            packet,
+            SignalElement::parse("[1,[[2,3]],[[]],[]]").unwrap().1
+        );
+        assert_ne!(
+            wrapped(SignalElement::Multiple(vec![]), 2),
+            wrapped(SignalElement::Multiple(vec![]), 1)
+        );
+        assert_ne!(
+            SignalElement::parse("[[1,2]]").unwrap().1,
+            SignalElement::parse("[1,2]").unwrap().1
+        );
+    }
+
+    #[test]
+    fn consistent_with_ord_and_hash() {
+        let mut seed = 0xe9;
+        let packets = (0..300)
+            .map(|_| random_packet(&mut seed, 0))
+            // Wraps are rare in random packets, so add some
+            .flat_map(|p| [wrapped(p.clone(), 1), p.normalized(), p])
+            .collect::<Vec<_>>();
+
+        for a in &packets {
+            let normal = a.normalized();
+            assert_eq!(
+                format!("{:?}", normal.normalized()),
+                format!("{:?}", normal)
+            );
+            for b in packets.iter().step_by(7) {
+                assert_eq!(a == b, a.cmp(b).is_eq(), "{} vs {}", a, b);
+                assert_eq!(a.cmp(b), normal.cmp(&b.normalized()), "{} vs {}", a, b);
+                assert_eq!(
+                    a == b,
+                    format!("{:?}", normal) == format!("{:?}", b.normalized())
+                );
+                if a == b {
+                    assert_eq!(hash(a), hash(b), "{} vs {}", a, b);
+                }
+            }
+        }
+
+        let hashed = packets.iter().collect::<HashSet<_>>();
+        let ordered = packets.iter().collect::<BTreeSet<_>>();
+        assert_eq!(hashed.len(), ordered.len());
+        assert!(hashed.len() < packets.len());
+    }
+
+    #[test]
+    fn test_cmp() {
+        assert_eq!(
+            SignalElement::Single(2),
+            SignalElement::Multiple(vec![SignalElement::Single(2)])
+        );
+        assert_eq!(
+            SignalElement::Multiple(vec![SignalElement::Single(2)]),
+            SignalElement::Single(2)
+        );
+        assert_eq!(SignalElement::Single(2), SignalElement::Single(2));
+        assert_eq!(
+            SignalElement::Multiple(vec![
+                SignalElement::Single(2),
+                SignalElement::Single(2),
+                SignalElement::Single(3),
+                SignalElement::Single(3)
+            ]),
+            SignalElement::Multiple(vec![
+                SignalElement::Single(2),
+                SignalElement::Single(2),
+                SignalElement::Single(3),
+                SignalElement::Single(3)
+            ])
+        );
+
+        assert!(
+            SignalElement::Single(3)
+                > SignalElement::Multiple(vec![
+                    SignalElement::Single(2),
+                    SignalElement::Single(1),
+                    SignalElement::Single(2)
+                ])
+        );
+        assert!(
+            SignalElement::Single(2)
+                < SignalElement::Multiple(vec![
+                    SignalElement::Single(2),
+                    SignalElement::Single(1),
+                    SignalElement::Single(2)
+                ])
+        );
+        assert!(
+            SignalElement::Single(4)
+                > SignalElement::Multiple(vec![
+                    SignalElement::Single(3),
+                    SignalElement::Single(1),
+                    SignalElement::Single(2)
+                ])
+        );
+        assert!(
+            SignalElement::Single(3)
+                > SignalElement::Multiple(vec![
+                    SignalElement::Single(2),
+                    SignalElement::Single(1),
+                    SignalElement::Single(2)
+                ])
+        );
+
+        assert!(
+            SignalElement::Multiple(vec![
+                SignalElement::Single(2),
+                SignalElement::Single(1),
+                SignalElement::Single(2),
+                SignalElement::Single(2)
+            ]) > SignalElement::Multiple(vec![
+                SignalElement::Single(2),
+                SignalElement::Single(1),
+                SignalElement::Single(2)
+            ])
+        );
+        assert!(SignalElement::Single(-3) < SignalElement::Single(2));
+        assert!(SignalElement::Single(99) < SignalElement::Text("a".to_string()));
+        assert!(
+            SignalElement::Text("b".to_string())
+                > SignalElement::Multiple(vec![SignalElement::Text("a".to_string())])
+        );
+        assert_eq!(
+            SignalElement::Text("a".to_string()),
+            SignalElement::Multiple(vec![SignalElement::Text("a".to_string())])
+        );
+
+        assert!(
+            SignalElement::Multiple(vec![
+                SignalElement::Single(2),
+                SignalElement::Single(1),
+                SignalElement::Single(2)
+            ]) < SignalElement::Multiple(vec![
+                SignalElement::Single(2),
+                SignalElement::Single(1),
+                SignalElement::Single(2),
+                SignalElement::Single(2)
+            ])
+        );
+
+        assert_eq!(
+            SignalElement::Multiple(vec![
+                SignalElement::Multiple(vec![
+                    SignalElement::Single(2),
+                    SignalElement::Single(3),
+                    SignalElement::Single(4)
+                ]),
+                SignalElement::Single(1),
+                SignalElement::Single(2)
+            ]),
+            SignalElement::Multiple(vec![
+                SignalElement::Multiple(vec![
+                    SignalElement::Single(2),
+                    SignalElement::Single(3),
+                    SignalElement::Single(4)
+                ]),
+                SignalElement::Single(1),
+                SignalElement::Single(2)
+            ])
+        );
+    }
+}
+
+impl SignalElement {
+    fn parse_single(input: &str) -> IResult<&str, Self> {
+        nom::combinator::map(integer, Self::Single)(input)
+    }
+
+    fn parse_text(input: &str) -> IResult<&str, Self> {
+        nom::combinator::map_res(json_string, |s: &str| {
+            serde_json::from_str(s).map(Self::Text)
+        })(input)
+    }
+
+    // A list nested `depth` lists deep at most, deeper ones fail outright instead of recursing
+    fn parse_multiple(input: &str, depth: usize) -> IResult<&str, Self> {
+        if depth == 0 && input.starts_with('[') {
+            return Err(nom::Err::Failure(nom::error::Error::new(
+                input,
+                ErrorKind::TooLarge,
+            )));
+        }
+
+        nom::combinator::map(
+            delimited(
+                pair(tag("["), multispace0),
+                separated_list0(tag(","), |i| Self::parse_with_max_depth(i, depth - 1)),
+                tag("]"),
+            ),
+            SignalElement::Multiple,
+        )(input)
+    }
+
+    // Whitespace is allowed around every element. Lists can nest up to `MAX_DEPTH` deep.
+    pub fn parse(input: &str) -> IResult<&str, Self> {
+        Self::parse_with_max_depth(input, MAX_DEPTH)
+    }
+
+    // Like `parse`, with lists nesting up to `max_depth` deep. `[]` is one deep.
+    pub fn parse_with_max_depth(input: &str, max_depth: usize) -> IResult<&str, Self> {
+        delimited(
+            multispace0,
+            nom::branch::alt((Self::parse_single, Self::parse_text, |i| {
+                Self::parse_multiple(i, max_depth)
+            })),
+            multispace0,
+        )(input)
+    }
+
+    // A JSON array of packets
+    pub fn from_json(json: &str) -> serde_json::Result<Vec<Self>> {
+        serde_json::from_str(json)
+    }
+}
+
+// Reproducible packets of every kind for the tests and benchmarks, not part of the API
+#[doc(hidden)]
+pub mod testing {
+    use crate::SignalElement;
+
+    // Numbers, odd strings and lists nested up to five deep, drawn from a xorshift of `seed`
+    pub fn random_packet(seed: &mut u64, depth: usize) -> SignalElement {
+        let mut next = || {
+            *seed ^= *seed << 13;
+            *seed ^= *seed >> 7;
+            *seed ^= *seed << 17;
+            *seed
+        };
+
+        match next() % 8 {
+            0 | 1 => SignalElement::Single(next() as i64 % 1000),
+            2 => {
+                let chars = ['a', 'Z', ' ', '"', '\\', '\n', 'é', '\u{1}', '['];
+                let len = next() % 5;
+                let text = (0..len).map(|_| chars[(next() % 9) as usize]).collect();
+                SignalElement::Text(text)
+            }
+            _ if depth >= 5 => SignalElement::Multiple(vec![]),
+            _ => {
+                let len = next() % 5;
+                SignalElement::Multiple((0..len).map(|_| random_packet(seed, depth + 1)).collect())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_parse {
+    use crate::testing::random_packet;
+    use crate::{SignalElement, MAX_DEPTH};
+
+    #[test]
+    fn display() {
+        let packet = SignalElement::parse("[1,[2,[3,[]]],\"x\"]").unwrap().1;
+
+        assert_eq!(packet.to_string(), "[1,[2,[3,[]]],\"x\"]");
+        assert_eq!(
+            format!("{:#}", packet),
+            "[\n  1,\n  [\n    2,\n    [\n      3,\n      []\n    ]\n  ],\n  \"x\"\n]"
+        );
+        assert_eq!(SignalElement::Single(-4).to_string(), "-4");
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let mut seed = 0x5eed;
+        for _ in 0..500 {
+            let packet = random_packet(&mut seed, 0);
+
+            for text in [packet.to_string(), format!("{:#}", packet)] {
+                let (rest, parsed) = SignalElement::parse(&text).unwrap();
+                assert_eq!(rest, "");
+                // `==` treats `2` and `[2]` alike, compare the structure instead
+                assert_eq!(format!("{:?}", parsed), format!("{:?}", packet), "{}", text);
+            }
+            assert_eq!(serde_json::to_string(&packet).unwrap(), packet.to_string());
+        }
+    }
+
+    #[test]
+    fn parse_single() {
+        let input = "2";
+        assert_eq!(
+            SignalElement::parse_single(input),
+            Ok(("", SignalElement::Single(2)))
+        );
+
+        let input_failed = "[2]";
+        assert!(SignalElement::parse_single(input_failed).is_err());
+    }
+
+    #[test]
+    fn parse_multiple() {
+        let input = "[2,3,4]";
+        assert_eq!(
+            SignalElement::parse_multiple(input, MAX_DEPTH),
+            Ok((
+                "",
+                SignalElement::Multiple(vec![
+                    SignalElement::Single(2),
+                    SignalElement::Single(3),
+                    SignalElement::Single(4)
+                ])
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_json() {
+        let input = " [ -2 , \"a\\\"b\" ,[ ], [\"\\u00e9\", 10 ] ] ";
+        assert_eq!(
+            SignalElement::parse(input),
+            Ok((
+                "",
+                SignalElement::Multiple(vec![
+                    SignalElement::Single(-2),
+                    SignalElement::Text("a\"b".to_string()),
+                    SignalElement::Multiple(vec![]),
+                    SignalElement::Multiple(vec![
+                        SignalElement::Text("é".to_string()),
+                        SignalElement::Single(10),
+                    ]),
+                ])
+            ))
+        );
+
+        let from_serde: SignalElement = serde_json::from_str(input).unwrap();
+        assert_eq!(from_serde, SignalElement::parse(input).unwrap().1);
+        assert_eq!(
+            serde_json::to_string(&from_serde).unwrap(),
+            "[-2,\"a\\\"b\",[],[\"é\",10]]"
+        );
+
+        assert!(SignalElement::from_json("[1, 2.5]").is_err());
+        assert!(SignalElement::from_json("[{\"a\": 1}]").is_err());
+    }
+
+    fn nested(depth: usize) -> String {
+        format!("{}1{}", "[".repeat(depth), "]".repeat(depth))
+    }
+
+    #[test]
+    fn max_depth() {
+        let deepest = nested(MAX_DEPTH);
+        let (rest, packet) = SignalElement::parse(&deepest).unwrap();
+        assert_eq!((rest, packet.to_string()), ("", deepest.clone()));
+        assert!(SignalElement::parse(&nested(MAX_DEPTH + 1)).is_err());
+
+        // serde_json has a limit of its own
+        let json = |depth| SignalElement::from_json(&format!("[{}]", nested(depth - 1)));
+        assert!(json(100).is_ok());
+        assert!(json(MAX_DEPTH + 1).is_err());
+        assert!(json(1_000_000).is_err());
+
+        assert!(SignalElement::parse_with_max_depth("[[1],2]", 2).is_ok());
+        assert!(SignalElement::parse_with_max_depth("[[[1]],2]", 2).is_err());
+        assert_eq!(
+            SignalElement::parse_with_max_depth("3", 0),
+            Ok(("", SignalElement::Single(3)))
+        );
+        assert!(SignalElement::parse_with_max_depth("[]", 0).is_err());
+    }
+
+    // Overflowed the stack before there was a limit
+    #[test]
+    fn deeply_nested() {
+        assert!(SignalElement::parse(&"[".repeat(1_000_000)).is_err());
+        assert!(SignalElement::parse(&nested(1_000_000)).is_err());
+    }
+
+    #[test]
+    fn parse_recursive() {
+        let input = "[2,[2,3],[4,5,[6,7]]]";
+        assert_eq!(
+            SignalElement::parse_multiple(input, MAX_DEPTH),
+            Ok((
+                "",
+                SignalElement::Multiple(vec![
+                    SignalElement::Single(2),
+                    SignalElement::Multiple(vec![
+                        SignalElement::Single(2),
+                        SignalElement::Single(3),
+                    ]),
+                    SignalElement::Multiple(vec![
+                        SignalElement::Single(4),
+                        SignalElement::Single(5),
+                        SignalElement::Multiple(vec![
+                            SignalElement::Single(6),
+                            SignalElement::Single(7),
+                        ]),
+                    ]),
+                ])
+            ))
+        );
+    }
+}
+
+// Why the input isn't a list of packet pairs. Lines are 1-based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    // The line isn't exactly one packet
+    Invalid { line: usize },
+    // The line's packet has lists nested deeper than `MAX_DEPTH`
+    TooDeep { line: usize },
+    // The block of lines starting at `line` has `packets` packets instead of two
+    NotAPair { line: usize, packets: usize },
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::Invalid { line } => write!(f, "line {}: invalid packet", line),
+            ParseError::TooDeep { line } => write!(
+                f,
+                "line {}: packet nests more than {} lists deep",
+                line, MAX_DEPTH
+            ),
+            ParseError::NotAPair { line, packets } => write!(
+                f,
+                "line {}: expected a pair of packets, found {}",
+                line, packets
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// The packet on line `number`, which has to be all of the line
+fn parse_line(line: &str, number: usize) -> Result<SignalElement, ParseError> {
+    match SignalElement::parse(line) {
+        Ok(("", packet)) => Ok(packet),
+        Err(nom::Err::Failure(e)) if e.code == ErrorKind::TooLarge => {
+            Err(ParseError::TooDeep { line: number })
+        }
+        _ => Err(ParseError::Invalid { line: number }),
+    }
+}
+
+// Parses packets one line at a time, as they're needed
+pub fn packets(input: &str) -> impl Iterator<Item = Result<SignalElement, ParseError>> + '_ {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| parse_line(line, i + 1))
+}
+
+pub fn parse_packets(input: &str) -> Result<Vec<SignalElement>, ParseError> {
+    packets(input).collect()
+}
+
+// Runs `f` on the packets of `input` as they're parsed, which stop at the first invalid line
+fn with_packets<T>(
+    input: &str,
+    f: impl FnOnce(&mut dyn Iterator<Item = SignalElement>) -> T,
+) -> Result<T, ParseError> {
+    let mut error = None;
+    let result = f(&mut packets(input).map_while(|p| p.map_err(|e| error = Some(e)).ok()));
+    error.map_or(Ok(result), Err)
+}
+
+// The packets of each block of lines between blank lines, which has to be a pair. An invalid line
+// anywhere is reported before a block that isn't a pair, the same as when there are no pairs.
+pub fn parse_pairs(input: &str) -> Result<Vec<(SignalElement, SignalElement)>, ParseError> {
+    let mut pairs = vec![];
+    let mut block = vec![];
+    let mut start = 1;
+    let mut not_a_pair = None;
+    // A blank line at the end closes the last block
+    for (i, line) in input.lines().chain([""]).enumerate() {
+        if !line.trim().is_empty() {
+            block.push(parse_line(line, i + 1)?);
+            continue;
+        }
+
+        match <[_; 2]>::try_from(std::mem::take(&mut block)) {
+            Ok([left, right]) => pairs.push((left, right)),
+            Err(block) if block.is_empty() => {}
+            Err(block) => {
+                not_a_pair.get_or_insert(ParseError::NotAPair {
+                    line: start,
+                    packets: block.len(),
+                });
+            }
+        }
+        start = i + 2;
+    }
+    not_a_pair.map_or(Ok(pairs), Err)
+}
+
+// Sums the 1-based indices of the pairs already in order
+pub fn ordered_pairs(pairs: &[(SignalElement, SignalElement)]) -> usize {
+    pairs
+        .iter()
+        .enumerate()
+        .filter(|(_, (left, right))| left <= right)
+        .map(|(i, _)| i + 1)
+        .sum()
+}
+
+// Where `packet` would land, 1-based, if it were sorted in with `packets`. Equal packets go first.
+pub fn rank_of<I>(packets: I, packet: &SignalElement) -> usize
+where
+    I: IntoIterator,
+    I::Item: Borrow<SignalElement>,
+{
+    1 + packets.into_iter().filter(|p| p.borrow() <= packet).count()
+}
+
+// The 1-based positions `dividers` end up at when they're added to `packets` and everything is
+// sorted, as a stable sort would place them: after equal packets, and in their own order when
+// dividers are equal. One pass over `packets`, which never get sorted or kept.
+pub fn divider_positions<I>(packets: I, dividers: &[SignalElement]) -> Vec<usize>
+where
+    I: IntoIterator,
+    I::Item: Borrow<SignalElement>,
+{
+    let mut positions = dividers
+        .iter()
+        .enumerate()
+        .map(|(i, divider)| {
+            let before = dividers
+                .iter()
+                .enumerate()
+                .filter(|(j, other)| *other < divider || (*other == divider && *j < i))
+                .count();
+            1 + before
+        })
+        .collect::<Vec<_>>();
+
+    for packet in packets {
+        for (position, divider) in positions.iter_mut().zip(dividers) {
+            if packet.borrow() <= divider {
+                *position += 1;
+            }
+        }
+    }
+    positions
+}
+
+pub fn decoder_key<I>(packets: I) -> usize
+where
+    I: IntoIterator,
+    I::Item: Borrow<SignalElement>,
+{
+    let dividers = [2, 6].map(|n| {
+        SignalElement::Multiple(vec![SignalElement::Multiple(vec![SignalElement::Single(
+            n,
+        )])])
+    });
+
+    divider_positions(packets, &dividers).into_iter().product()
+}
+
+pub fn part1(input: &str) -> Result<usize, ParseError> {
+    Ok(ordered_pairs(&parse_pairs(input)?))
+}
+
+pub fn part2(input: &str) -> Result<usize, ParseError> {
+    with_packets(input, |packets| decoder_key(packets))
+}
+
+// Where `packet` would land if it were sorted in with the packets of `input`
+pub fn rank_in(input: &str, packet: &SignalElement) -> Result<usize, ParseError> {
+    with_packets(input, |packets| rank_of(packets, packet))
+}
+
+#[cfg(test)]
+mod test {
+
+    const INPUT: &str = include_str!("../input-example.txt");
+    #[test]
+    fn part1() {
+        assert_eq!(super::part1(INPUT), Ok(13));
+    }
+
+    #[test]
+    fn part2() {
+        assert_eq!(super::part2(INPUT), Ok(140));
+    }
+
+    #[test]
+    fn invalid_input() {
+        use super::ParseError;
+
+        // A bad line is an error, instead of being skipped and shifting every later pair
+        let input = "[1,[2]\n[1,2]\n\n[3]\n[4]\n\n[5]\n[4]\n";
+        assert_eq!(super::part1(input), Err(ParseError::Invalid { line: 1 }));
+        assert_eq!(super::part2(input), Err(ParseError::Invalid { line: 1 }));
+        assert_eq!(
+            super::part1("[1]\n[1,2]]\n"),
+            Err(ParseError::Invalid { line: 2 })
+        );
+        assert_eq!(
+            super::part2("[1]\n\n[2] x\n"),
+            Err(ParseError::Invalid { line: 3 })
+        );
+
+        assert_eq!(
+            super::part1("[1]\n[2]\n\n\n[3]\n[4]\n[5]\n\n[6]\n[7]"),
+            Err(ParseError::NotAPair {
+                line: 5,
+                packets: 3
+            })
+        );
+        assert_eq!(
+            super::part1("[1]\n[2]\n\n[3]"),
+            Err(ParseError::NotAPair {
+                line: 4,
+                packets: 1
+            })
+        );
+        assert_eq!(super::part1("[1]\n[2]\n\n\n[3]\n[3]\n\n"), Ok(3));
+    }
+
+    #[test]
+    fn too_deep() {
+        use super::{ParseError, MAX_DEPTH};
+
+        let deep = format!("{}{}", "[".repeat(MAX_DEPTH + 1), "]".repeat(MAX_DEPTH + 1));
+        let input = format!("[1]\n[2]\n\n[3]\n{}\n\n[5]\n[4]\n", deep);
+        assert_eq!(super::part1(&input), Err(ParseError::TooDeep { line: 5 }));
+        assert_eq!(super::part2(&input), Err(ParseError::TooDeep { line: 5 }));
+        assert_eq!(
+            ParseError::TooDeep { line: 5 }.to_string(),
+            "line 5: packet nests more than 128 lists deep"
+        );
+
+        let deepest = format!("{}{}", "[".repeat(MAX_DEPTH), "]".repeat(MAX_DEPTH));
+        assert_eq!(super::part1(&format!("[[]]\n{}\n", deepest)), Ok(1));
+    }
+
+    #[test]
+    fn json() {
+        use super::{decoder_key, ordered_pairs, parse_pairs, SignalElement};
+
+        let packets = SignalElement::from_json(&format!(
+            "[{}]",
+            INPUT.split_whitespace().collect::<Vec<_>>().join(",")
+        ))
+        .unwrap();
+        let pairs = packets
+            .chunks(2)
+            .map(|pair| (pair[0].clone(), pair[1].clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(pairs, parse_pairs(INPUT).unwrap());
+        assert_eq!(ordered_pairs(&pairs), 13);
+        assert_eq!(decoder_key(packets), 140);
+    }
+
+    #[test]
+    fn rank_of() {
+        use super::{rank_in, SignalElement};
+
+        let rank = |packet: &str| rank_in(INPUT, &SignalElement::parse(packet).unwrap().1).unwrap();
+        // Sorted, the example starts with [], [[]], [[[]]] and ends with [[8,7,6]], [9]
+        assert_eq!(rank("[]"), 2);
+        assert_eq!(rank("[[[]]]"), 4);
+        assert_eq!(rank("[[2]]"), 10);
+        assert_eq!(rank("[[6]]"), 13);
+        assert_eq!(rank("[9]"), 17);
+        assert_eq!(rank("10"), 17);
+    }
+
+    #[test]
+    fn divider_positions_match_sorting() {
+        use super::testing::random_packet;
+        use super::{divider_positions, SignalElement};
+
+        let mut seed = 0xd1;
+        for _ in 0..50 {
+            let packets = (0..40)
+                .map(|_| random_packet(&mut seed, 0))
+                .collect::<Vec<_>>();
+            // Some dividers equal to packets or to each other, to check ties
+            let mut dividers = (0..3)
+                .map(|_| random_packet(&mut seed, 0))
+                .collect::<Vec<_>>();
+            dividers.push(packets[0].clone());
+            dividers.push(SignalElement::Multiple(vec![dividers[0].clone()]));
+
+            let mut sorted = packets
+                .iter()
+                .map(|p| (p, None))
+                .chain(dividers.iter().enumerate().map(|(i, d)| (d, Some(i))))
+                .collect::<Vec<_>>();
+            sorted.sort_by(|a, b| a.0.cmp(b.0));
+            let mut expected = vec![0; dividers.len()];
+            for (position, (_, divider)) in sorted.iter().enumerate() {
+                if let Some(i) = divider {
+                    expected[*i] = position + 1;
+                }
+            }
+
+            assert_eq!(divider_positions(&packets, &dividers), expected);
+        }
+    }
+}
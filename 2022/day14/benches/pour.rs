@@ -0,0 +1,48 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use day14::{flood_fill, parse_grid, pour, pour_from_source, SOURCE};
+
+// Rock paths scattered over a cave much wider and deeper than the puzzle's
+fn cave(seed: u64) -> String {
+    let mut state = seed;
+    let mut next = |n: usize| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % n as u64) as usize
+    };
+
+    (0..150)
+        .map(|_| {
+            let (mut x, mut y) = (380 + next(240), 40 + next(150));
+            let mut path = vec![format!("{},{}", x, y)];
+            for turn in 0..2 + next(4) {
+                if turn % 2 == 0 {
+                    x = (x + next(20)).saturating_sub(10);
+                } else {
+                    y = (y + next(10)).saturating_sub(5).max(40);
+                }
+                path.push(format!("{},{}", x, y));
+            }
+            path.join(" -> ") + "\n"
+        })
+        .collect()
+}
+
+fn floor(c: &mut Criterion) {
+    let cave = cave(0x5a4d);
+    let grid = |floor| parse_grid(&cave, floor).unwrap();
+    let mut group = c.benchmark_group("floor");
+    group.bench_function("pour from source", |b| {
+        b.iter(|| pour_from_source(&mut black_box(grid(true)), SOURCE))
+    });
+    group.bench_function("pour resuming", |b| {
+        b.iter(|| pour(&mut black_box(grid(true)), SOURCE))
+    });
+    group.bench_function("flood fill", |b| {
+        b.iter(|| flood_fill(&black_box(grid(true)), SOURCE))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, floor);
+criterion_main!(benches);
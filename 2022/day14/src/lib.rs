@@ -0,0 +1,614 @@
+#![feature(iter_intersperse)]
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Display;
+
+use nom::bytes::complete::tag;
+use nom::character::complete::{digit1, line_ending};
+use nom::combinator::{all_consuming, map, map_res};
+use nom::multi::separated_list1;
+use nom::sequence::separated_pair;
+use nom::IResult;
+
+pub const SOURCE: Point = Point { x: 500, y: 0 };
+
+#[derive(Debug, Hash, PartialEq, PartialOrd, Ord, Eq, Clone, Copy)]
+pub struct Point {
+    x: usize,
+    y: usize,
+}
+
+impl From<(usize, usize)> for Point {
+    fn from((x, y): (usize, usize)) -> Self {
+        Self { x, y }
+    }
+}
+
+impl Point {
+    fn down(&self) -> Self {
+        (self.x, self.y + 1).into()
+    }
+    // The cave ends at column 0, there's nothing to the left of it
+    fn left_down(&self) -> Option<Self> {
+        Some((self.x.checked_sub(1)?, self.y + 1).into())
+    }
+    fn right_down(&self) -> Self {
+        (self.x + 1, self.y + 1).into()
+    }
+}
+
+struct Path(Vec<Point>);
+
+struct Bound {
+    max: Point,
+    min: Point,
+}
+
+struct Boundaries {
+    bound: Option<Bound>,
+}
+
+struct BoundaryIterator<'a> {
+    boundaries: &'a Boundaries,
+    current: Option<Point>,
+    done: bool,
+}
+
+impl<'a> Iterator for BoundaryIterator<'a> {
+    type Item = Point;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if let Some(bound) = &self.boundaries.bound {
+            if let Some(c) = self.current {
+                let next_point: Option<Point> = if c.x == bound.max.x {
+                    if c.y == bound.max.y {
+                        self.done = true;
+                        None
+                    } else {
+                        Some((bound.min.x, c.y + 1).into())
+                    }
+                } else {
+                    Some((c.x + 1, c.y).into())
+                };
+                self.current = next_point;
+            } else {
+                self.current = Some(bound.min);
+            }
+            self.current
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_iter {
+    use crate::Boundaries;
+
+    #[test]
+    fn iter() {
+        let point1 = (3, 3).into();
+        let point2 = (5, 2).into();
+
+        let mut boundaries = Boundaries { bound: None };
+        let v = boundaries.enumerate().collect::<Vec<_>>();
+        assert_eq!(v, vec![]);
+
+        boundaries.grow_to_include(&point1);
+        boundaries.grow_to_include(&point2);
+        let v = boundaries.enumerate().collect::<Vec<_>>();
+
+        assert_eq!(
+            v,
+            vec![
+                (3, 2).into(),
+                (4, 2).into(),
+                (5, 2).into(),
+                (3, 3).into(),
+                (4, 3).into(),
+                (5, 3).into()
+            ]
+        )
+    }
+}
+
+impl Boundaries {
+    fn in_bound(&self, point: &Point) -> bool {
+        if let Some(bound) = &self.bound {
+            !(point.x < bound.min.x
+                || point.y < bound.min.y
+                || point.x > bound.max.x
+                || point.y > bound.max.y)
+        } else {
+            false
+        }
+    }
+
+    fn grow_to_include(&mut self, point: &Point) {
+        if let Some(bound) = &mut self.bound {
+            bound.max.x = bound.max.x.max(point.x);
+            bound.min.x = bound.min.x.min(point.x);
+            bound.max.y = bound.max.y.max(point.y);
+            bound.min.y = bound.min.y.min(point.y);
+        } else {
+            self.bound = Some(Bound {
+                max: *point,
+                min: *point,
+            })
+        }
+    }
+
+    fn enumerate(&self) -> BoundaryIterator<'_> {
+        BoundaryIterator {
+            boundaries: self,
+            current: None,
+            done: false,
+        }
+    }
+
+    fn x_len(&self) -> usize {
+        if let Some(b) = &self.bound {
+            b.max.x - b.min.x + 1
+        } else {
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_boundaries {
+    use crate::Boundaries;
+
+    #[test]
+    fn bound() {
+        let mut b = Boundaries { bound: None };
+        let point = (2, 7).into();
+        let point2 = (4, 3).into();
+        let point_mid = (3, 5).into();
+
+        assert!(!b.in_bound(&point));
+        assert!(!b.in_bound(&point2));
+        assert!(!b.in_bound(&point_mid));
+
+        b.grow_to_include(&point);
+        assert!(b.in_bound(&point));
+        assert!(!b.in_bound(&point2));
+        assert!(!b.in_bound(&point_mid));
+
+        b.grow_to_include(&point2);
+        assert!(b.in_bound(&point));
+        assert!(b.in_bound(&point2));
+        assert!(b.in_bound(&point_mid));
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Element {
+    Void,
+    Air,
+    Rock,
+    Sand,
+}
+
+pub struct Grid {
+    map: HashMap<Point, Element>,
+    boundaries: Boundaries,
+    // An endless line of rock at this height, if there is one
+    floor: Option<usize>,
+}
+
+impl Display for Grid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let drawing = self
+            .boundaries
+            .enumerate()
+            .map(|point| match self.get(&point) {
+                Element::Void | Element::Air => '.',
+                Element::Rock => '#',
+                Element::Sand => 'o',
+            })
+            .collect::<Vec<_>>()
+            .chunks(self.boundaries.x_len())
+            .intersperse(&['\n'])
+            .flatten()
+            .collect::<String>();
+        f.write_str(&drawing)
+    }
+}
+
+impl Grid {
+    fn new() -> Self {
+        Self {
+            map: HashMap::new(),
+            boundaries: Boundaries { bound: None },
+            floor: None,
+        }
+    }
+
+    // Sand this low falls forever
+    fn below_everything(&self, point: &Point) -> bool {
+        match &self.boundaries.bound {
+            Some(bound) => self.floor.is_none() && point.y > bound.max.y,
+            None => self.floor.is_none(),
+        }
+    }
+
+    // Puts the floor two below the lowest rock
+    fn add_floor(&mut self) {
+        if let Some(bound) = &self.boundaries.bound {
+            self.floor = Some(bound.max.y + 2);
+        }
+    }
+
+    fn get(&self, point: &Point) -> Element {
+        if self.floor == Some(point.y) {
+            return Element::Rock;
+        }
+        match self.map.get(point) {
+            Some(element) => *element,
+            None => {
+                if self.boundaries.in_bound(point) {
+                    Element::Air
+                } else {
+                    Element::Void
+                }
+            }
+        }
+    }
+
+    fn add(&mut self, point: Point, element: Element) -> Option<Element> {
+        self.map.insert(point, element)
+    }
+
+    fn add_path(&mut self, path: Path, element: Element) {
+        path.0
+            .into_iter()
+            .fold(None, |cursor: Option<Point>, point| {
+                self.boundaries.grow_to_include(&point);
+                if let Some(c) = cursor {
+                    if c.y == point.y {
+                        let min = c.x.min(point.x);
+                        let max = c.x.max(point.x);
+                        for px in min..=max {
+                            self.add((px, point.y).into(), element);
+                        }
+                    }
+                    if c.x == point.x {
+                        let min = c.y.min(point.y);
+                        let max = c.y.max(point.y);
+                        for py in min..=max {
+                            self.add((point.x, py).into(), element);
+                        }
+                    }
+                }
+                Some(point)
+            });
+    }
+}
+
+// Where a grain of sand ends up
+#[derive(Debug, PartialEq, Eq)]
+enum Fall {
+    // It can't get to where it's dropped from, something's already there
+    Blocked,
+    Rest(Point),
+    // It falls below every rock, and keeps falling forever
+    Abyss,
+}
+
+// Drops grains of sand one after the other, without recursing. A grain falls the same way as the
+// one before it did until it gets to where that one came to rest, so each grain starts from the
+// last free position on the previous one's path instead of from the source.
+struct Dropper {
+    path: Vec<Point>,
+}
+
+impl Dropper {
+    fn new(source: Point) -> Self {
+        Self { path: vec![source] }
+    }
+
+    // Where the next grain ends up. It's up to the caller to put it there.
+    fn drop(&mut self, grid: &Grid) -> Fall {
+        let free = |point: &Point| matches!(grid.get(point), Element::Void | Element::Air);
+        while let Some(from) = self.path.last() {
+            if !free(from) {
+                self.path.pop();
+                continue;
+            }
+            if grid.below_everything(from) {
+                return Fall::Abyss;
+            }
+            match [Some(from.down()), from.left_down(), Some(from.right_down())]
+                .into_iter()
+                .flatten()
+                .find(free)
+            {
+                Some(next) => self.path.push(next),
+                None => return Fall::Rest(*from),
+            }
+        }
+        Fall::Blocked
+    }
+}
+
+// Drops sand from `source` until a grain falls into the abyss or the source is blocked, returning
+// how many grains came to rest
+pub fn pour(grid: &mut Grid, source: Point) -> usize {
+    let mut dropper = Dropper::new(source);
+    let mut grains = 0;
+    while let Fall::Rest(point) = dropper.drop(grid) {
+        grid.add(point, Element::Sand);
+        grid.boundaries.grow_to_include(&point);
+        grains += 1;
+    }
+    grains
+}
+
+// Same as `pour`, but every grain is dropped from the source like the puzzle describes
+pub fn pour_from_source(grid: &mut Grid, source: Point) -> usize {
+    let mut grains = 0;
+    while let Fall::Rest(point) = Dropper::new(source).drop(grid) {
+        grid.add(point, Element::Sand);
+        grid.boundaries.grow_to_include(&point);
+        grains += 1;
+    }
+    grains
+}
+
+// How many grains `pour` would leave in a cave with a floor, without dropping any. Sand ends up in
+// every position it can fall to from the source: below the source, above the floor, not rock,
+// and diagonally or straight under another one of those. `None` without a floor.
+pub fn flood_fill(grid: &Grid, source: Point) -> Option<usize> {
+    let floor = grid.floor?;
+    let free =
+        |x: usize, y: usize| matches!(grid.get(&(x, y).into()), Element::Void | Element::Air);
+
+    let mut row = BTreeSet::new();
+    if free(source.x, source.y) {
+        row.insert(source.x);
+    }
+    let mut grains = 0;
+    for y in source.y + 1..=floor {
+        grains += row.len();
+        row = row
+            .iter()
+            .flat_map(|x| [x.checked_sub(1), Some(*x), Some(x + 1)])
+            .flatten()
+            .filter(|x| free(*x, y))
+            .collect();
+    }
+    Some(grains)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {}: expected a path like `498,4 -> 498,6`",
+            self.line
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn parse_path(input: &str) -> IResult<&str, Path> {
+    map(separated_list1(tag(" -> "), parse_point), Path)(input)
+}
+
+fn parse_coordinate(input: &str) -> IResult<&str, usize> {
+    map_res(digit1, str::parse)(input)
+}
+
+fn parse_point(input: &str) -> IResult<&str, Point> {
+    map(
+        separated_pair(parse_coordinate, tag(","), parse_coordinate),
+        Point::from,
+    )(input)
+}
+
+// One path per line, and everything but whitespace at the end has to be paths
+pub fn parse_grid(input: &str, floor: bool) -> Result<Grid, ParseError> {
+    let input = input.trim_end();
+    let (_, paths) =
+        all_consuming(separated_list1(line_ending, parse_path))(input).map_err(|e| {
+            let rest = match e {
+                nom::Err::Error(e) | nom::Err::Failure(e) => e.input,
+                nom::Err::Incomplete(_) => "",
+            };
+            // Past the line break that ends the last path that parsed, if that's where it stopped
+            let rest = rest
+                .strip_prefix('\n')
+                .or(rest.strip_prefix("\r\n"))
+                .unwrap_or(rest);
+            let parsed = &input[..input.len() - rest.len()];
+            ParseError {
+                line: parsed.matches('\n').count() + 1,
+            }
+        })?;
+
+    let mut grid = Grid::new();
+
+    for path in paths {
+        grid.add_path(path, Element::Rock);
+    }
+    if floor {
+        grid.add_floor();
+    }
+
+    Ok(grid)
+}
+
+pub fn part1(input: &str) -> Result<i64, ParseError> {
+    Ok(pour(&mut parse_grid(input, false)?, SOURCE) as i64)
+}
+
+pub fn part2(input: &str) -> Result<i64, ParseError> {
+    Ok(flood_fill(&parse_grid(input, true)?, SOURCE).expect("a floor") as i64)
+}
+
+#[cfg(test)]
+mod test {
+
+    const INPUT: &str = include_str!("../input-example.txt");
+    #[test]
+    fn part1() {
+        assert_eq!(super::part1(INPUT), Ok(24));
+    }
+
+    #[test]
+    fn part2() {
+        assert_eq!(super::part2(INPUT), Ok(93));
+    }
+
+    #[test]
+    fn draw() {
+        use super::{parse_grid, pour, SOURCE};
+
+        let mut grid = parse_grid(INPUT, false).unwrap();
+        pour(&mut grid, SOURCE);
+
+        // The puzzle's drawing, from the highest grain down
+        assert_eq!(
+            grid.to_string(),
+            "\
+......o...
+.....ooo..
+....#ooo##
+...o#ooo#.
+..###ooo#.
+....oooo#.
+.o.ooooo#.
+#########."
+        );
+    }
+
+    #[test]
+    fn dropper() {
+        use super::{parse_grid, Dropper, Element, Fall, SOURCE};
+
+        let mut grid = parse_grid(INPUT, false).unwrap();
+        assert_eq!(
+            Dropper::new(SOURCE).drop(&grid),
+            Fall::Rest((500, 8).into())
+        );
+        assert_eq!(Dropper::new((493, 0).into()).drop(&grid), Fall::Abyss);
+
+        grid.add(SOURCE, Element::Sand);
+        assert_eq!(Dropper::new(SOURCE).drop(&grid), Fall::Blocked);
+
+        let floor = parse_grid(INPUT, true).unwrap();
+        assert_eq!(
+            Dropper::new((480, 0).into()).drop(&floor),
+            Fall::Rest((480, 10).into())
+        );
+    }
+
+    #[test]
+    fn long_fall() {
+        use super::{parse_grid, Dropper, Fall, SOURCE};
+
+        // Far deeper than the stack would have allowed when dropping recursed
+        let grid = parse_grid("499,1000000 -> 501,1000000\n", false).unwrap();
+        assert_eq!(
+            Dropper::new(SOURCE).drop(&grid),
+            Fall::Rest((500, 999_999).into())
+        );
+    }
+
+    #[test]
+    fn resuming_matches_dropping_from_source() {
+        use super::{parse_grid, pour, pour_from_source, SOURCE};
+
+        let input = include_str!("../input.txt");
+        for (input, floor) in [(INPUT, false), (INPUT, true), (input, false)] {
+            let (mut resumed, mut from_source) = (
+                parse_grid(input, floor).unwrap(),
+                parse_grid(input, floor).unwrap(),
+            );
+
+            assert_eq!(
+                pour(&mut resumed, SOURCE),
+                pour_from_source(&mut from_source, SOURCE)
+            );
+            assert_eq!(resumed.to_string(), from_source.to_string());
+        }
+    }
+
+    #[test]
+    fn flood_fill_matches_pouring() {
+        use super::{flood_fill, parse_grid, pour, SOURCE};
+
+        for input in [INPUT, include_str!("../input.txt")] {
+            let grains = pour(&mut parse_grid(input, true).unwrap(), SOURCE);
+            assert_eq!(
+                flood_fill(&parse_grid(input, true).unwrap(), SOURCE),
+                Some(grains)
+            );
+            assert_eq!(flood_fill(&parse_grid(input, false).unwrap(), SOURCE), None);
+        }
+    }
+
+    #[test]
+    fn left_edge() {
+        use super::{flood_fill, parse_grid, pour, pour_from_source, Dropper, Fall};
+
+        // The floor is deep enough for sand to pile up against column 0
+        let input = "0,5 -> 1,5\n";
+        let source = (3, 0).into();
+        let grains = pour(&mut parse_grid(input, true).unwrap(), source);
+
+        assert_eq!(
+            pour_from_source(&mut parse_grid(input, true).unwrap(), source),
+            grains
+        );
+        assert_eq!(
+            flood_fill(&parse_grid(input, true).unwrap(), source),
+            Some(grains)
+        );
+        assert_eq!(
+            Dropper::new((0, 0).into()).drop(&parse_grid(input, false).unwrap()),
+            Fall::Rest((0, 4).into())
+        );
+    }
+
+    #[test]
+    fn line_endings() {
+        use super::{part1, part2};
+
+        let crlf = INPUT.replace('\n', "\r\n");
+        for input in [INPUT.trim_end(), &crlf, &format!("{}\n\n", INPUT)] {
+            assert_eq!(part1(input), Ok(24));
+            assert_eq!(part2(input), Ok(93));
+        }
+    }
+
+    #[test]
+    fn invalid_input() {
+        use super::{parse_grid, ParseError};
+
+        for (input, line) in [
+            ("498,4 -> 498,6\n503,4 -> 502\n", 2),
+            ("498,4 -> 498,6\n\n503,4 -> 502,4\n", 2),
+            ("498,4 -> 498,6 ->\n", 1),
+            ("99999999999999999999999,4 -> 1,4\n", 1),
+            ("", 1),
+        ] {
+            assert_eq!(
+                parse_grid(input, false).err(),
+                Some(ParseError { line }),
+                "{:?}",
+                input
+            );
+        }
+    }
+}
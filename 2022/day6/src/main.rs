@@ -1,7 +1,14 @@
+mod monitor;
 use std::collections::HashSet;
-use std::io::Read;
+use std::io::{Read, Write};
+
+use self::monitor::{MarkerKind, Monitor};
 
 fn main() {
+    if std::env::args().any(|arg| arg == "--monitor") {
+        return run_monitor();
+    }
+
     let mut buffer = vec![];
     std::io::stdin().read_to_end(&mut buffer).unwrap();
     let input = String::from_utf8(buffer).unwrap();
@@ -13,6 +20,26 @@ fn main() {
     println!("part2: {}", result_2);
 }
 
+fn run_monitor() {
+    let stdout = std::io::stdout();
+    for event in Monitor::new(std::io::stdin().lock()) {
+        let event = event.unwrap();
+        let kind = match event.kind {
+            MarkerKind::StartOfPacket => "start-of-packet",
+            MarkerKind::StartOfMessage => "start-of-message",
+        };
+
+        let mut out = stdout.lock();
+        writeln!(
+            out,
+            "{} on line {} at {}: {}",
+            kind, event.line, event.offset, event.marker
+        )
+        .unwrap();
+        out.flush().unwrap();
+    }
+}
+
 fn first_occurence_of_distinct_n_chars(input: &str, n: usize) -> usize {
     let (pos, _) = input
         .chars()
@@ -0,0 +1,238 @@
+use std::collections::{HashSet, VecDeque};
+use std::io::Read;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MarkerKind {
+    StartOfPacket,
+    StartOfMessage,
+}
+
+impl MarkerKind {
+    pub fn size(&self) -> usize {
+        match self {
+            MarkerKind::StartOfPacket => 4,
+            MarkerKind::StartOfMessage => 14,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Event {
+    pub kind: MarkerKind,
+    // 1-based line of the stream the marker is on
+    pub line: usize,
+    // byte offset in the line right after the marker, same as the puzzle answer
+    pub offset: usize,
+    pub marker: String,
+}
+
+struct Detector {
+    kind: MarkerKind,
+    window: VecDeque<u8>,
+}
+
+impl Detector {
+    fn new(kind: MarkerKind) -> Self {
+        Self {
+            kind,
+            window: VecDeque::with_capacity(kind.size()),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+    }
+
+    fn push(&mut self, byte: u8, line: usize, offset: usize) -> Option<Event> {
+        if self.window.len() == self.kind.size() {
+            self.window.pop_front();
+        }
+        self.window.push_back(byte);
+
+        if self.window.len() < self.kind.size() {
+            return None;
+        }
+
+        let mut uniq = HashSet::new();
+        if !self.window.iter().all(|b| uniq.insert(b)) {
+            return None;
+        }
+
+        let marker = self.window.drain(..).map(char::from).collect();
+        Some(Event {
+            kind: self.kind,
+            line,
+            offset,
+            marker,
+        })
+    }
+}
+
+// Reads the stream as it arrives and emits an event every time a marker is found. After a
+// detector fires it starts over with an empty window, so a long-running stream can report
+// several packets and messages. Every line is a datastream of its own: markers never span a line
+// break and offsets count from the start of the line.
+pub struct Monitor<R: Read> {
+    reader: R,
+    buffer: Vec<u8>,
+    line: usize,
+    offset: usize,
+    detectors: Vec<Detector>,
+    pending: VecDeque<Event>,
+}
+
+impl<R: Read> Monitor<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: vec![0; 1024],
+            line: 1,
+            offset: 0,
+            detectors: vec![
+                Detector::new(MarkerKind::StartOfPacket),
+                Detector::new(MarkerKind::StartOfMessage),
+            ],
+            pending: VecDeque::new(),
+        }
+    }
+
+    fn feed(&mut self, len: usize) {
+        for &byte in &self.buffer[..len] {
+            match byte {
+                b'\n' => {
+                    self.line += 1;
+                    self.offset = 0;
+                    self.detectors.iter_mut().for_each(Detector::reset);
+                    continue;
+                }
+                b'\r' => continue,
+                _ => self.offset += 1,
+            }
+
+            for detector in self.detectors.iter_mut() {
+                if let Some(event) = detector.push(byte, self.line, self.offset) {
+                    self.pending.push_back(event);
+                }
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for Monitor<R> {
+    type Item = std::io::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(Ok(event));
+            }
+
+            match self.reader.read(&mut self.buffer) {
+                Ok(0) => return None,
+                Ok(len) => self.feed(len),
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => (),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::VecDeque;
+    use std::io::Read;
+
+    use super::{Event, MarkerKind, Monitor};
+
+    struct ChunkedReader {
+        chunks: VecDeque<Vec<u8>>,
+    }
+
+    impl ChunkedReader {
+        fn new(chunks: &[&str]) -> Self {
+            Self {
+                chunks: chunks.iter().map(|c| c.as_bytes().to_vec()).collect(),
+            }
+        }
+    }
+
+    impl Read for ChunkedReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match self.chunks.pop_front() {
+                None => Ok(0),
+                Some(mut chunk) => {
+                    let len = chunk.len().min(buf.len());
+                    buf[..len].copy_from_slice(&chunk[..len]);
+                    if len < chunk.len() {
+                        self.chunks.push_front(chunk.split_off(len));
+                    }
+                    Ok(len)
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn markers_across_chunks() {
+        let reader = ChunkedReader::new(&["mj", "qjp", "qmgbljsphdz", "tnvjfqwrcgsmlb\n"]);
+
+        let events = Monitor::new(reader)
+            .collect::<std::io::Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(
+            events.first(),
+            Some(&Event {
+                kind: MarkerKind::StartOfPacket,
+                line: 1,
+                offset: 7,
+                marker: "jpqm".to_owned(),
+            })
+        );
+        assert_eq!(
+            events.iter().find(|e| e.kind == MarkerKind::StartOfMessage),
+            Some(&Event {
+                kind: MarkerKind::StartOfMessage,
+                line: 1,
+                offset: 19,
+                marker: "qmgbljsphdztnv".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn single_byte_chunks() {
+        let input = include_str!("../input-example.txt");
+        let chunks = input
+            .char_indices()
+            .map(|(i, _)| &input[i..i + 1])
+            .collect::<Vec<_>>();
+
+        let packet_offsets = Monitor::new(ChunkedReader::new(&chunks))
+            .filter_map(Result::ok)
+            .filter(|e| e.kind == MarkerKind::StartOfPacket)
+            .map(|e| e.offset)
+            .collect::<Vec<_>>();
+
+        assert_eq!(packet_offsets.first(), Some(&crate::part1(input)));
+    }
+
+    #[test]
+    fn markers_never_span_lines() {
+        let reader = ChunkedReader::new(&["ab\n", "cd\r\n", "bvwbjplbgvbhsrlpgd", "mjqwftvncz\n"]);
+
+        let events = Monitor::new(reader)
+            .collect::<std::io::Result<Vec<_>>>()
+            .unwrap();
+
+        // Same answers as the puzzle gives for `bvwbjplbgvbhsrlpgdmjqwftvncz` on its own
+        let first = |kind| {
+            events
+                .iter()
+                .find(|e| e.kind == kind)
+                .map(|e| (e.line, e.offset))
+        };
+        assert_eq!(first(MarkerKind::StartOfPacket), Some((3, 5)));
+        assert_eq!(first(MarkerKind::StartOfMessage), Some((3, 23)));
+    }
+}
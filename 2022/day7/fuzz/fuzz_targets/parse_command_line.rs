@@ -0,0 +1,19 @@
+#![no_main]
+
+use day7::filesystem::Filesystem;
+use day7::parser::parse_command_line;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    let mut commands = vec![];
+    let mut rest = input;
+    while let Ok((next, command)) = parse_command_line(rest) {
+        // Every command consumes something, so this can't loop forever
+        assert!(next.len() < rest.len());
+        commands.push(command);
+        rest = next;
+    }
+
+    let fs = Filesystem::build(commands.into_iter());
+    fs.root.borrow().total_size();
+});
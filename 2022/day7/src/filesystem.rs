@@ -69,12 +69,13 @@ impl std::fmt::Debug for Directory {
 }
 
 impl Directory {
+    // Saturates at `i64::MAX` instead of overflowing when sizes in the input are absurdly large
     pub fn total_size(&self) -> i64 {
         self.entities.iter().fold(0, |acc, entity| {
-            acc + match &*entity.borrow() {
+            acc.saturating_add(match &*entity.borrow() {
                 DirectoryEntity::File(f) => f.borrow().size,
                 DirectoryEntity::Dir(d) => d.borrow().total_size(),
-            }
+            })
         })
     }
 }
@@ -256,4 +257,16 @@ mod test {
 
         assert_eq!(fs.root.borrow().total_size(), 600);
     }
+
+    // Found by fuzzing: used to overflow
+    #[test]
+    fn fs_size_saturates() {
+        let commands = vec![CommandWithOutput::LS(vec![
+            OutputDirectoryEntity::File((6, "cg".to_owned())),
+            OutputDirectoryEntity::File((i64::MAX, "8ji03".to_owned())),
+        ])];
+        let fs = Filesystem::build(commands.into_iter());
+
+        assert_eq!(fs.root.borrow().total_size(), i64::MAX);
+    }
 }
@@ -1,5 +1,3 @@
-mod filesystem;
-mod parser;
 use std::cell::RefCell;
 use std::io::Read;
 use std::rc::Rc;
@@ -7,7 +5,8 @@ use std::rc::Rc;
 use nom::multi::many1;
 use nom::IResult;
 
-use self::filesystem::{CommandWithOutput, Directory, Filesystem};
+use day7::filesystem::{self, CommandWithOutput, Directory, Filesystem};
+use day7::parser;
 
 fn main() {
     let mut buffer = vec![];